@@ -0,0 +1,228 @@
+use crate::expression::Expression;
+use crate::parser::Stmt;
+
+/// Renders the program as a Graphviz `digraph`, with one node per statement
+/// or expression and an edge from each node to its children.
+pub fn to_dot(statements: &[Stmt]) -> String {
+    let mut graph = Graph::default();
+    statements.iter().for_each(|statement| {
+        graph.stmt(statement);
+    });
+    format!("digraph ast {{\n{}}}\n", graph.out)
+}
+
+#[derive(Default)]
+struct Graph {
+    out: String,
+    nodes: usize,
+}
+
+impl Graph {
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+        self.out
+            .push_str(&format!("    n{id} [label=\"{label}\"];\n"));
+        id
+    }
+
+    fn edge(&mut self, parent: usize, child: usize) {
+        self.out.push_str(&format!("    n{parent} -> n{child};\n"));
+    }
+
+    /// Adds a node with the given children, returning its id.
+    fn branch(&mut self, label: &str, children: impl FnOnce(&mut Self) -> Vec<usize>) -> usize {
+        let id = self.node(label);
+        for child in children(self) {
+            self.edge(id, child);
+        }
+        id
+    }
+
+    fn stmts(&mut self, statements: &[Stmt]) -> Vec<usize> {
+        statements.iter().map(|s| self.stmt(s)).collect()
+    }
+
+    fn stmt(&mut self, statement: &Stmt) -> usize {
+        let label = |label: &Option<String>| match label {
+            Some(label) => format!(" {label}"),
+            None => String::new(),
+        };
+        match statement {
+            Stmt::Var { name, initializer } => self.branch(&format!("Var {name}"), |g| {
+                initializer.iter().map(|e| g.expr(e)).collect()
+            }),
+            Stmt::Const { name, initializer } => {
+                self.branch(&format!("Const {name}"), |g| vec![g.expr(initializer)])
+            }
+            Stmt::Expr(expression) => self.branch("Expr", |g| vec![g.expr(expression)]),
+            Stmt::Block(statements) => self.branch("Block", |g| g.stmts(statements)),
+            Stmt::If {
+                branches,
+                else_stmt,
+            } => self.branch("If", |g| {
+                let (condition, body) = &branches[0];
+                let mut children = vec![g.expr(condition), g.stmt(body)];
+                for (condition, body) in &branches[1..] {
+                    children.push(g.branch("Else If", |g| vec![g.expr(condition), g.stmt(body)]));
+                }
+                if let Some(else_stmt) = else_stmt {
+                    children.push(g.branch("Else", |g| vec![g.stmt(else_stmt)]));
+                }
+                children
+            }),
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+            } => self.branch("Switch", |g| {
+                let mut children = vec![g.expr(subject)];
+                for (value, body) in cases {
+                    children.push(g.branch("Case", |g| vec![g.expr(value), g.stmt(body)]));
+                }
+                if let Some(default) = default {
+                    children.push(g.branch("Default", |g| vec![g.stmt(default)]));
+                }
+                children
+            }),
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                label: name,
+            } => self.branch(&format!("While{}", label(name)), |g| {
+                let mut children = vec![g.expr(condition), g.stmt(body)];
+                if let Some(increment) = increment {
+                    children.push(g.expr(increment));
+                }
+                children
+            }),
+            Stmt::DoWhile {
+                body,
+                condition,
+                label: name,
+            } => self.branch(&format!("DoWhile{}", label(name)), |g| {
+                vec![g.stmt(body), g.expr(condition)]
+            }),
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                label: loop_label,
+            } => self.branch(&format!("ForIn{} {name}", label(loop_label)), |g| {
+                vec![g.expr(iterable), g.stmt(body)]
+            }),
+            Stmt::Break(name) => self.node(&format!("Break{}", label(name))),
+            Stmt::Continue(name) => self.node(&format!("Continue{}", label(name))),
+            Stmt::Return(value) => {
+                self.branch("Return", |g| value.iter().map(|e| g.expr(e)).collect())
+            }
+            Stmt::Function {
+                name,
+                body,
+                parameters,
+                defaults,
+            } => self.branch(
+                &format!(
+                    "Function {name}({})",
+                    parameters
+                        .iter()
+                        .map(|p| p.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                |g| {
+                    let mut children = defaults.iter().map(|e| g.expr(e)).collect::<Vec<_>>();
+                    children.extend(g.stmts(body));
+                    children
+                },
+            ),
+            Stmt::Class {
+                name,
+                methods,
+                static_methods,
+                getters,
+            } => self.branch(&format!("Class {name}"), |g| {
+                let mut children = g.stmts(methods);
+                for method in static_methods {
+                    children.push(g.branch("Static", |g| vec![g.stmt(method)]));
+                }
+                for getter in getters {
+                    children.push(g.branch("Getter", |g| vec![g.stmt(getter)]));
+                }
+                children
+            }),
+            Stmt::Decorated { function, wrapped } => {
+                self.branch("Decorated", |g| vec![g.expr(wrapped), g.stmt(function)])
+            }
+            Stmt::Null => self.node("Null"),
+        }
+    }
+
+    fn exprs(&mut self, expressions: &[Expression]) -> Vec<usize> {
+        expressions.iter().map(|e| self.expr(e)).collect()
+    }
+
+    fn expr(&mut self, expression: &Expression) -> usize {
+        match expression {
+            Expression::Literal(_) => self.node(&expression.repr()),
+            Expression::Unary { op, right } => self.branch(&op.lexeme, |g| vec![g.expr(right)]),
+            Expression::Binary { left, op, right } | Expression::Logical { left, op, right } => {
+                self.branch(&op.lexeme, |g| vec![g.expr(left), g.expr(right)])
+            }
+            Expression::Grouping(expression) => self.branch("group", |g| vec![g.expr(expression)]),
+            Expression::Variable { name, .. } => self.node(name),
+            Expression::Assign(name, value) => {
+                self.branch(&format!("{name} ="), |g| vec![g.expr(value)])
+            }
+            Expression::PostfixUpdate { name, op, .. } => {
+                self.node(&format!("{name}{0}{0}", op.lexeme))
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } => self.branch("call", |g| {
+                let mut children = vec![g.expr(callee)];
+                children.extend(g.exprs(arguments));
+                children
+            }),
+            Expression::Get { name, object } => {
+                self.branch(&format!(".{name}"), |g| vec![g.expr(object)])
+            }
+            Expression::Set {
+                object,
+                name,
+                value,
+            } => self.branch(&format!(".{name}="), |g| {
+                vec![g.expr(object), g.expr(value)]
+            }),
+            Expression::Index { object, index } => {
+                self.branch("[]", |g| vec![g.expr(object), g.expr(index)])
+            }
+            Expression::SetIndex {
+                object,
+                index,
+                value,
+            } => self.branch("[]=", |g| {
+                vec![g.expr(object), g.expr(index), g.expr(value)]
+            }),
+            Expression::Slice { object, start, end } => {
+                self.branch("[:]", |g| vec![g.expr(object), g.expr(start), g.expr(end)])
+            }
+            Expression::Array(elements) => self.branch("array", |g| g.exprs(elements)),
+            Expression::Map(entries) => self.branch("map", |g| {
+                entries
+                    .iter()
+                    .flat_map(|(key, value)| [g.expr(key), g.expr(value)])
+                    .collect()
+            }),
+            Expression::Interpolation(parts) => self.branch("interpolation", |g| g.exprs(parts)),
+            Expression::This => self.node("this"),
+            Expression::Block(statements, value) => self.branch("block", |g| {
+                let mut children = g.stmts(statements);
+                children.push(g.expr(value));
+                children
+            }),
+        }
+    }
+}
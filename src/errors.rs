@@ -1,7 +1,9 @@
 use crate::scanner::Token;
 use colored::Colorize;
+use std::env;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::io::{self, IsTerminal};
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -10,12 +12,21 @@ pub enum LoxError {
     Parse(ParseError),
     Runtime(RuntimeError),
     Internal(String),
+    /// Raised by `exit` and `quit` to stop the program: it is up to the host to
+    /// end the process with this code.
+    Exit(i32),
 }
 
 use LoxError::*;
 impl Error for LoxError {}
 pub type LoxResult<T> = Result<T, LoxError>;
 
+/// Errors are colored only when stderr is a terminal and `NO_COLOR` is not set.
+pub fn init_coloring() {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    colored::control::set_override(!no_color && io::stderr().is_terminal());
+}
+
 impl LoxError {
     fn why(&self) -> String {
         match self {
@@ -27,9 +38,36 @@ impl LoxError {
             }) => {
                 format!("{}:{}: {} ({})", line, col, message, r#type)
             }
-            Parse(ParseError { token, message }) => format!("{} (on token `{}`)", message, token),
-            Runtime(RuntimeError { message }) => message.to_owned(),
+            Parse(ParseError {
+                token,
+                message,
+                after: false,
+            }) => format!("line {}: {} (on token `{}`)", token.line, message, token),
+            Parse(ParseError { token, message, .. }) => {
+                format!("line {}: {} (after token `{}`)", token.line, message, token)
+            }
+            Runtime(RuntimeError {
+                message,
+                line: Some(line),
+            }) => format!("line {line}: {message}"),
+            Runtime(RuntimeError { message, .. }) => message.to_owned(),
             Internal(message) => message.to_owned(),
+            Exit(code) => format!("exit with code {code}"),
+        }
+    }
+
+    /// Attaches the line of the call site to a runtime error raised by a call,
+    /// unless a more precise line was already attached.
+    pub fn at_line(self, line: usize) -> Self {
+        match self {
+            Runtime(RuntimeError {
+                message,
+                line: None,
+            }) => Runtime(RuntimeError {
+                message,
+                line: Some(line),
+            }),
+            e => e,
         }
     }
 }
@@ -58,6 +96,7 @@ impl Display for LoxError {
             Scan(_) | Parse(_) => "syntax error:",
             Runtime(_) => "runtime error:",
             Internal(_) => "internal error:",
+            Exit(_) => return write!(f, "{}", self.why()),
         };
         write!(f, "{} {}", prefix.red(), self.why())
     }
@@ -68,11 +107,15 @@ impl Display for LoxError {
 #[derive(Debug)]
 pub struct RuntimeError {
     message: String,
+    line: Option<usize>,
 }
 
 impl RuntimeError {
     pub fn build(message: String) -> LoxError {
-        Runtime(Self { message })
+        Runtime(Self {
+            message,
+            line: None,
+        })
     }
 }
 
@@ -80,11 +123,28 @@ impl RuntimeError {
 pub struct ParseError {
     token: Token,
     message: String,
+    /// Whether the error is about something missing after `token`, rather than
+    /// about `token` itself.
+    after: bool,
 }
 
 impl ParseError {
     pub fn build(token: Token, message: String) -> LoxError {
-        Parse(Self { token, message })
+        Parse(Self {
+            token,
+            message,
+            after: false,
+        })
+    }
+
+    /// An error about something missing after `token`, such as a `;` ending the
+    /// statement it belongs to.
+    pub fn after(token: Token, message: String) -> LoxError {
+        Parse(Self {
+            token,
+            message,
+            after: true,
+        })
     }
 }
 
@@ -93,6 +153,7 @@ pub enum ScanErrorType {
     UnexpectedCharacter,
     InvalidNumber,
     UnterminatedString,
+    InvalidEscape,
 }
 
 impl Display for ScanErrorType {
@@ -101,6 +162,7 @@ impl Display for ScanErrorType {
             ScanErrorType::UnexpectedCharacter => write!(f, "unexpected character"),
             ScanErrorType::InvalidNumber => write!(f, "invalid number"),
             ScanErrorType::UnterminatedString => write!(f, "unterminated string"),
+            ScanErrorType::InvalidEscape => write!(f, "invalid escape sequence"),
         }
     }
 }
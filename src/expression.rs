@@ -1,35 +1,151 @@
 use crate::errors::{LoxResult, RuntimeError};
-use crate::functions::Callable;
+use crate::functions::{string_method, BoundMethod, Callable, CallableRef, CallableType};
 use crate::interpreter::Environment;
+use crate::parser::Stmt;
 use crate::scanner::{Token, TokenType};
+use crate::symbol::Symbol;
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
-use std::ops::Not;
+use std::ops::{Not, Range};
 use std::rc::Rc;
 use Expression::*;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Object {
     Str(String),
     Number(f64),
     Bool(bool),
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "serialize_callable",
+            deserialize_with = "deserialize_callable"
+        )
+    )]
     Callable(Rc<RefCell<dyn Callable>>),
+    Bytes(Rc<RefCell<Vec<u8>>>),
+    Array(Rc<RefCell<Vec<Object>>>),
+    Map(Rc<RefCell<HashMap<String, Object>>>),
     Nil,
 }
 
+/// Callables are serialized as their name and arity, so they cannot be read back.
+#[cfg(feature = "serde")]
+fn serialize_callable<S: serde::Serializer>(
+    callable: &Rc<RefCell<dyn Callable>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeStruct;
+    let callable = callable.borrow();
+    let mut state = serializer.serialize_struct("Callable", 2)?;
+    state.serialize_field("name", callable.name())?;
+    state.serialize_field("arity", &callable.arity())?;
+    state.end()
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_callable<'de, D: serde::Deserializer<'de>>(
+    _deserializer: D,
+) -> Result<Rc<RefCell<dyn Callable>>, D::Error> {
+    Err(serde::de::Error::custom("callables cannot be deserialized"))
+}
+
 impl Object {
     pub fn callable(&self) -> bool {
         matches!(self, Object::Callable(..))
     }
 
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Object::Number(x) if x.fract() == 0.0)
+    }
+
+    /// The value of a whole number within the range of an `i64`, as taken by the
+    /// bitwise operators, `hex` and `bin`.
+    pub fn as_integer(&self) -> Option<i64> {
+        // -2^63 and 2^63 are exact as floats, unlike i64::MAX
+        const BOUND: f64 = -(i64::MIN as f64);
+        match self {
+            Object::Number(x) if self.is_integer() && (-BOUND..BOUND).contains(x) => {
+                Some(*x as i64)
+            }
+            _ => None,
+        }
+    }
+
     pub fn r#type(&self) -> String {
         match self {
             Object::Str(_) => "<string> object".to_string(),
             Object::Number(_) => "<f64> object".to_string(),
             Object::Bool(_) => "<bool> object".to_string(),
             Object::Callable(f) => format!("<{}> object", f.borrow().r#type()),
+            Object::Bytes(_) => "<bytes> object".to_string(),
+            Object::Array(_) => "<array> object".to_string(),
+            Object::Map(_) => "<map> object".to_string(),
             Object::Nil => "<nil> object".to_string(),
         }
     }
+
+    /// The name of the type as returned by `typeof`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Str(_) => "string",
+            Object::Number(_) => "number",
+            Object::Bool(_) => "bool",
+            Object::Callable(f) => match f.borrow().r#type() {
+                CallableType::Function => "function",
+                CallableType::Class => "class",
+                CallableType::Instance => "instance",
+            },
+            Object::Bytes(_) => "bytes",
+            Object::Array(_) => "array",
+            Object::Map(_) => "map",
+            Object::Nil => "nil",
+        }
+    }
+
+    /// The unambiguous form of the object given by `Debug`, as echoed by the REPL
+    /// and returned by `repr`: strings are quoted and escaped (`"a\nb"`) and
+    /// numbers keep their decimal part (`2.0`).
+    pub fn repr(&self) -> String {
+        format!("{self:?}")
+    }
+
+    /// The object as written in the source, for the messages and traces that show
+    /// values: strings are quoted, numbers are not.
+    pub(crate) fn literal(&self) -> String {
+        match self {
+            Object::Str(s) => format!("{s:?}"),
+            object => object.to_string(),
+        }
+    }
+
+    /// The text of the object as printed or concatenated, given by the `to_string`
+    /// method of the instances that have one.
+    pub fn display(&self, env: &mut Environment) -> LoxResult<String> {
+        let method = match self {
+            Object::Callable(f) if matches!(f.borrow().r#type(), CallableType::Instance) => {
+                f.borrow().get("to_string").ok()
+            }
+            _ => None,
+        };
+        let Some(Object::Callable(method)) = method else {
+            return Ok(self.to_string());
+        };
+        let method = method.borrow();
+        if method.arity() != 0 {
+            return Err(RuntimeError::build(
+                "`to_string` must take no argument".to_string(),
+            ));
+        }
+        match method.call(vec![], env)? {
+            Object::Str(s) => Ok(s),
+            value => Err(RuntimeError::build(format!(
+                "`to_string` must return a string but returned {}",
+                value.type_name()
+            ))),
+        }
+    }
 }
 
 impl From<Object> for bool {
@@ -45,13 +161,29 @@ impl Not for Object {
     }
 }
 
+/// Functions and classes are shown with their name (`<fn f>`, `<class A>`), instances
+/// with the name of their class (`<A instance>`).
+fn callable_repr(callable: &dyn Callable) -> String {
+    match callable.r#type() {
+        CallableType::Function => format!("<fn {}>", callable.name()),
+        CallableType::Class => format!("<class {}>", callable.name()),
+        CallableType::Instance => format!("<{} instance>", callable.name()),
+    }
+}
+
+/// The `Debug` representation is the one returned by `repr`: numbers always keep
+/// their floating point form (`2.0`, `1e20`) and strings are quoted.
 impl Debug for Object {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Object::Str(s) => write!(f, "{s:?}"),
             Object::Number(x) => write!(f, "{x:?}"),
             Object::Bool(b) => write!(f, "{b:?}"),
-            Object::Callable(c) => write!(f, "{}", c.borrow()),
+            Object::Callable(c) => write!(f, "{}", callable_repr(&*c.borrow())),
+            Object::Bytes(b) => write!(f, "{}", bytes_repr(&b.borrow())),
+            Object::Array(_) | Object::Map(_) => {
+                write!(f, "{}", element_repr(self, true, &mut vec![]))
+            }
             Object::Nil => write!(f, "nil"),
         }
     }
@@ -64,12 +196,46 @@ impl PartialEq for Object {
             (Str(s1), Str(s2)) => s1 == s2,
             (Bool(b1), Bool(b2)) => b1 == b2,
             (Number(x1), Number(x2)) => x1 == x2,
+            (Bytes(b1), Bytes(b2)) => b1 == b2,
+            (Array(_), Array(_)) | (Map(_), Map(_)) => {
+                containers_eq(self, other, &mut HashSet::new())
+            }
             (Nil, Nil) => true,
             _ => false,
         }
     }
 }
 
+/// Compares arrays and maps element by element. `visited` holds the pairs of
+/// containers being compared, so that a container holding itself is equal to
+/// itself instead of recursing forever.
+fn containers_eq(a: &Object, b: &Object, visited: &mut HashSet<(usize, usize)>) -> bool {
+    match (a, b) {
+        (Object::Array(a), Object::Array(b)) => {
+            if !visited.insert((Rc::as_ptr(a) as usize, Rc::as_ptr(b) as usize)) {
+                return true;
+            }
+            let (a, b) = (a.borrow(), b.borrow());
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b.iter())
+                    .all(|(a, b)| containers_eq(a, b, visited))
+        }
+        (Object::Map(a), Object::Map(b)) => {
+            if !visited.insert((Rc::as_ptr(a) as usize, Rc::as_ptr(b) as usize)) {
+                return true;
+            }
+            let (a, b) = (a.borrow(), b.borrow());
+            a.len() == b.len()
+                && a.iter().all(|(key, value)| {
+                    b.get(key)
+                        .is_some_and(|other| containers_eq(value, other, visited))
+                })
+        }
+        (a, b) => a == b,
+    }
+}
+
 impl Clone for Object {
     fn clone(&self) -> Self {
         match self {
@@ -78,10 +244,16 @@ impl Clone for Object {
             Object::Bool(b) => Object::Bool(*b),
             Object::Nil => Object::Nil,
             Object::Callable(f) => Object::Callable(Rc::clone(f)),
+            Object::Bytes(b) => Object::Bytes(Rc::clone(b)),
+            Object::Array(a) => Object::Array(Rc::clone(a)),
+            Object::Map(m) => Object::Map(Rc::clone(m)),
         }
     }
 }
 
+/// Numbers are all stored as `f64`. Whole-valued numbers are displayed without a
+/// decimal part (`2.0` prints as `2`), any other number is displayed with the
+/// shortest representation that round-trips (`10 / 4` prints as `2.5`).
 impl Display for Object {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         use Object::*;
@@ -91,12 +263,66 @@ impl Display for Object {
             Number(x) => write!(f, "{x}"),
             Bool(b) => write!(f, "{b}"),
             Nil => write!(f, "nil"),
-            Callable(c) => write!(f, "{}", c.borrow()),
+            Callable(c) => write!(f, "{}", callable_repr(&*c.borrow())),
+            Bytes(b) => write!(f, "{}", bytes_repr(&b.borrow())),
+            Array(_) | Map(_) => write!(f, "{}", element_repr(self, false, &mut vec![])),
         }
     }
 }
 
+fn bytes_repr(bytes: &[u8]) -> String {
+    let escaped = bytes
+        .iter()
+        .flat_map(|b| std::ascii::escape_default(*b))
+        .map(char::from)
+        .collect::<String>();
+    format!("b\"{escaped}\"")
+}
+
+/// The form of an element of an array or map: strings are quoted, and other
+/// objects use their `Debug` form if `debug` is set, their `Display` form
+/// otherwise. `visiting` holds the containers being printed, so that a
+/// container holding itself is shown as `[...]` or `{...}`.
+fn element_repr(object: &Object, debug: bool, visiting: &mut Vec<usize>) -> String {
+    match object {
+        Object::Array(a) => {
+            let ptr = Rc::as_ptr(a) as usize;
+            if visiting.contains(&ptr) {
+                return "[...]".to_string();
+            }
+            visiting.push(ptr);
+            let elements = a
+                .borrow()
+                .iter()
+                .map(|o| element_repr(o, debug, visiting))
+                .collect::<Vec<_>>();
+            visiting.pop();
+            format!("[{}]", elements.join(", "))
+        }
+        Object::Map(m) => {
+            let ptr = Rc::as_ptr(m) as usize;
+            if visiting.contains(&ptr) {
+                return "{...}".to_string();
+            }
+            visiting.push(ptr);
+            let map = m.borrow();
+            let mut keys = map.keys().collect::<Vec<_>>();
+            keys.sort();
+            let entries = keys
+                .into_iter()
+                .map(|key| format!("{key:?}: {}", element_repr(&map[key], debug, visiting)))
+                .collect::<Vec<_>>();
+            visiting.pop();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Object::Str(s) => format!("{s:?}"),
+        object if debug => format!("{object:?}"),
+        object => object.to_string(),
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expression {
     Literal(Object),
     Unary {
@@ -109,8 +335,18 @@ pub enum Expression {
         right: Box<Expression>,
     },
     Grouping(Box<Expression>),
-    Variable(String),
-    Assign(String, Box<Expression>),
+    Variable {
+        name: Symbol,
+        line: usize,
+    },
+    Assign(Symbol, Box<Expression>),
+    /// `name++` or `name--`, setting the variable to its value plus or minus one
+    /// with `op`, and giving the value it had before.
+    PostfixUpdate {
+        name: Symbol,
+        op: Token,
+        line: usize,
+    },
     Logical {
         left: Box<Expression>,
         op: Token,
@@ -119,6 +355,7 @@ pub enum Expression {
     Call {
         callee: Box<Expression>,
         arguments: Vec<Expression>,
+        line: usize,
     },
     Get {
         name: String,
@@ -129,179 +366,760 @@ pub enum Expression {
         name: String,
         value: Box<Expression>,
     },
+    Index {
+        object: Box<Expression>,
+        index: Box<Expression>,
+    },
+    SetIndex {
+        object: Box<Expression>,
+        index: Box<Expression>,
+        value: Box<Expression>,
+    },
+    Slice {
+        object: Box<Expression>,
+        start: Box<Expression>,
+        end: Box<Expression>,
+    },
+    Array(Vec<Expression>),
+    Map(Vec<(Expression, Expression)>),
+    /// The literal parts and the expressions of an interpolated string, each
+    /// displayed as if printed and concatenated.
+    Interpolation(Vec<Expression>),
     This,
+    /// Statements run in a new scope, followed by the expression giving the value.
+    Block(Vec<Stmt>, Box<Expression>),
 }
 
 impl Expression {
     pub fn evaluate(&self, env: &mut Environment) -> LoxResult<Object> {
-        use Object::*;
+        // the operations are applied by separate functions, so that the frame of
+        // this one, which grows with each level of nesting, stays small
         match self {
             Literal(object) => Ok(object.clone()),
-            Unary { op, right } => {
-                let right = right.evaluate(env)?;
-                match &op.r#type {
-                    TokenType::Bang => Ok(Bool(right.into())),
-                    TokenType::Minus => {
-                        if let Number(x) = right {
-                            Ok(Number(-x))
-                        } else {
-                            Err(RuntimeError::build(
-                                "unary operator `-` only works with numbers".to_string(),
-                            ))
-                        }
-                    }
-                    token => Err(RuntimeError::build(format!(
-                        "invalid token for unary expression: `{:?}`",
-                        token
-                    ))),
-                }
-            }
+            Unary { op, right } => unary(op, right.evaluate(env)?),
             Binary { left, op, right } => {
                 let left = left.evaluate(env)?;
                 let right = right.evaluate(env)?;
-                match (left, &op.r#type, right) {
-                    (left, TokenType::EqualEqual, right) => Ok(Bool(left == right)),
-                    (left, TokenType::BangEqual, right) => Ok(Bool(left != right)),
-                    (Number(x), op, Number(y)) => match &op {
-                        TokenType::Plus => Ok(Number(x + y)),
-                        TokenType::Minus => Ok(Number(x - y)),
-                        TokenType::Slash => {
-                            if y == 0.0 {
-                                Err(RuntimeError::build("division by zero".to_string()))
-                            } else {
-                                Ok(Number(x / y))
-                            }
-                        }
-                        TokenType::Star => Ok(Number(x * y)),
-                        TokenType::Greater => Ok(Bool(x > y)),
-                        TokenType::GreaterEqual => Ok(Bool(x >= y)),
-                        TokenType::Less => Ok(Bool(x < y)),
-                        TokenType::LessEqual => Ok(Bool(x <= y)),
-                        op => Err(RuntimeError::build(format!(
-                            "unsupported operation between numbers: `{:?}`",
-                            op
-                        ))),
-                    },
-                    (Str(s1), op, Str(s2)) => match &op {
-                        TokenType::Plus => Ok(Str(s1.to_owned() + &s2)),
-                        TokenType::Greater => Ok(Bool(s1 > s2)),
-                        TokenType::GreaterEqual => Ok(Bool(s1 >= s2)),
-                        TokenType::Less => Ok(Bool(s1 < s2)),
-                        TokenType::LessEqual => Ok(Bool(s1 <= s2)),
-                        op => Err(RuntimeError::build(format!(
-                            "unsupported operation between strings: `{:?}`",
-                            op
-                        ))),
-                    },
-                    (Str(s1), TokenType::Plus, right) if right != Nil => {
-                        Ok(Str(format!("{}{}", s1, right)))
-                    }
-                    (left, TokenType::Plus, Str(s2)) if left != Nil => {
-                        Ok(Str(format!("{}{}", left, s2)))
-                    }
-                    _ => Err(RuntimeError::build(
-                        "can't evaluate expression: unsupported operation between types"
-                            .to_string(),
-                    )),
-                }
+                binary(left, op, right, env)
             }
             Grouping(expr) => expr.evaluate(env),
-            Variable(name) => env
-                .get(name)?
-                .as_ref()
-                .ok_or(RuntimeError::build(format!(
-                    "variable `{name}` used uninitialized"
-                )))
-                .cloned(),
+            Variable { name, line } => variable(*name, *line, env),
             Assign(name, expr) => {
-                let eval = expr.evaluate(env)?;
-                env.update(name, eval.clone())?;
-                Ok(eval)
-            }
-            Logical { left, op, right } => {
-                let left = left.evaluate(env)?;
-                if let TokenType::Or = op.r#type {
-                    if left.into() {
-                        return Ok(Bool(true));
-                    }
-                } else if !left {
-                    return Ok(Bool(false));
-                }
-                Ok(Bool(right.evaluate(env)?.into()))
-            }
-            Call { callee, arguments } => {
-                // callee is a Variable, get the object living in the env
-                let name = callee.to_string();
-                let callee = callee.evaluate(env)?;
-                if let Callable(f) = callee {
-                    let arity = f.borrow().arity();
-                    let num_args = arguments.len();
-                    if num_args != arity {
-                        return Err(RuntimeError::build(format!(
-                            "`{}`: expected {arity} argument{} but got {num_args}",
-                            f.borrow(),
-                            if arity > 1 { 's' } else { '\0' },
-                        )));
-                    }
-                    let objects = arguments
-                        .iter()
-                        .map(|arg| arg.evaluate(env))
-                        .collect::<Result<Vec<_>, _>>()?;
-                    f.borrow().call(objects, env)
-                } else {
-                    Err(RuntimeError::build(format!(
-                        "{name} is not is not callable"
-                    )))
-                }
-            }
-            Get { name, object } => {
-                if let Callable(f) = object.evaluate(env)? {
-                    f.borrow().get(name)
-                } else {
-                    Err(RuntimeError::build(format!(
-                        "{name} is not is not callable"
-                    )))
-                }
+                let value = expr.evaluate(env)?;
+                assign(*name, value, env)
             }
+            PostfixUpdate { name, op, line } => postfix_update(*name, op, *line, env),
+            Logical { left, op, right } => logical(left, op, right, env),
+            Call {
+                callee,
+                arguments,
+                line,
+            } => call(callee, arguments, *line, env),
+            Get { name, object } => get(object.evaluate(env)?, name, env),
             Set {
                 object,
                 name,
                 value,
             } => {
-                if let Callable(f) = object.evaluate(env)? {
-                    let value = value.evaluate(env)?;
-                    f.borrow_mut().set(name, value.clone())?;
-                    Ok(value)
-                } else {
-                    Err(RuntimeError::build(format!("{name} is not not callable")))
-                }
+                let object = object.evaluate(env)?;
+                set(object, name, value, env)
+            }
+            Index { object, index } => {
+                let object = object.evaluate(env)?;
+                get_index(object, index.evaluate(env)?)
+            }
+            SetIndex {
+                object,
+                index,
+                value,
+            } => {
+                let object = object.evaluate(env)?;
+                let index = index.evaluate(env)?;
+                set_index(object, index, value.evaluate(env)?)
+            }
+            Slice { object, start, end } => {
+                let object = object.evaluate(env)?;
+                let start = start.evaluate(env)?;
+                slice(object, start, end.evaluate(env)?)
             }
+            Map(entries) => map(entries, env),
+            Array(elements) => array(elements, env),
+            Interpolation(parts) => interpolation(parts, env),
             This => todo!(),
+            Block(statements, value) => block(statements, value, env),
         }
     }
 }
 
-impl Display for Expression {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            Literal(object) => object.to_string(),
-            Unary { op, right } => format!("({} {})", op, right),
-            Binary { left, op, right } => {
-                format!("({} {} {})", op, left, right)
+fn variable(name: Symbol, line: usize, env: &mut Environment) -> LoxResult<Object> {
+    env.get(name)
+        .and_then(|value| {
+            value.as_ref().cloned().ok_or(RuntimeError::build(format!(
+                "variable `{name}` used uninitialized"
+            )))
+        })
+        .map_err(|e| e.at_line(line))
+}
+
+fn assign(name: Symbol, value: Object, env: &mut Environment) -> LoxResult<Object> {
+    env.update(name, value.clone())?;
+    Ok(value)
+}
+
+fn postfix_update(
+    name: Symbol,
+    op: &Token,
+    line: usize,
+    env: &mut Environment,
+) -> LoxResult<Object> {
+    let old = variable(name, line, env)?;
+    let new = binary(old.clone(), op, Object::Number(1.0), env)?;
+    env.update(name, new)?;
+    Ok(old)
+}
+
+fn logical(
+    left: &Expression,
+    op: &Token,
+    right: &Expression,
+    env: &mut Environment,
+) -> LoxResult<Object> {
+    if op.r#type == TokenType::QuestionQuestion {
+        return match left.evaluate(env)? {
+            Object::Nil => right.evaluate(env),
+            left => Ok(left),
+        };
+    }
+    let left: bool = left.evaluate(env)?.into();
+    match op.r#type {
+        TokenType::Or if left => return Ok(Object::Bool(true)),
+        TokenType::And if !left => return Ok(Object::Bool(false)),
+        _ => (),
+    }
+    // unlike `and` and `or`, `xor` always evaluates both sides
+    let right: bool = right.evaluate(env)?.into();
+    Ok(Object::Bool(if op.r#type == TokenType::Xor {
+        left != right
+    } else {
+        right
+    }))
+}
+
+fn call(
+    callee: &Expression,
+    arguments: &[Expression],
+    line: usize,
+    env: &mut Environment,
+) -> LoxResult<Object> {
+    let (f, objects) = prepare_call(callee, arguments, env)?;
+    let result = f.borrow().call(objects, env);
+    result.map_err(|e| e.at_line(line))
+}
+
+fn set(object: Object, name: &str, value: &Expression, env: &mut Environment) -> LoxResult<Object> {
+    if let Object::Callable(f) = object {
+        let value = value.evaluate(env)?;
+        f.borrow_mut().set(name, value.clone())?;
+        Ok(value)
+    } else {
+        Err(RuntimeError::build(format!("{name} is not not callable")))
+    }
+}
+
+/// Concatenates the displayed values of the parts of an interpolated string.
+fn interpolation(parts: &[Expression], env: &mut Environment) -> LoxResult<Object> {
+    let mut s = String::new();
+    for part in parts {
+        let text = part.evaluate(env)?.display(env)?;
+        env.check_string_len(s.len() + text.len())?;
+        s.push_str(&text);
+    }
+    Ok(Object::Str(s))
+}
+
+fn array(elements: &[Expression], env: &mut Environment) -> LoxResult<Object> {
+    let objects = elements
+        .iter()
+        .map(|element| element.evaluate(env))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Object::Array(Rc::new(RefCell::new(objects))))
+}
+
+fn unary(op: &Token, right: Object) -> LoxResult<Object> {
+    use Object::*;
+    match &op.r#type {
+        TokenType::Bang => Ok(Bool(!right)),
+        TokenType::Minus => {
+            if let Number(x) = right {
+                Ok(Number(-x))
+            } else {
+                Err(RuntimeError::build(
+                    "unary operator `-` only works with numbers".to_string(),
+                ))
+            }
+        }
+        TokenType::Tilde => {
+            if let Number(x) = right {
+                Ok(Number(!integer_operand(x, op)? as f64))
+            } else {
+                Err(RuntimeError::build(
+                    "unary operator `~` only works with numbers".to_string(),
+                ))
+            }
+        }
+        token => Err(RuntimeError::build(format!(
+            "invalid token for unary expression: `{:?}`",
+            token
+        ))),
+    }
+}
+
+fn binary(left: Object, op: &Token, right: Object, env: &mut Environment) -> LoxResult<Object> {
+    use Object::*;
+    if let Some(result) = overloaded_operator(&left, op, &right, env)? {
+        return Ok(result);
+    }
+    match (left, &op.r#type, right) {
+        (left, TokenType::EqualEqual, right) => Ok(Bool(left == right)),
+        (left, TokenType::BangEqual, right) => Ok(Bool(left != right)),
+        (left, TokenType::Instanceof, right) => instance_of(&left, &right).map(Bool),
+        (Number(x), r#type, Number(y)) => match r#type {
+            TokenType::Plus => finite(x + y, op),
+            TokenType::Minus => finite(x - y, op),
+            TokenType::Slash | TokenType::Percent => divide(x, y, op),
+            TokenType::Star => finite(x * y, op),
+            TokenType::Greater => Ok(Bool(x > y)),
+            TokenType::GreaterEqual => Ok(Bool(x >= y)),
+            TokenType::Less => Ok(Bool(x < y)),
+            TokenType::LessEqual => Ok(Bool(x <= y)),
+            TokenType::Ampersand
+            | TokenType::Pipe
+            | TokenType::Caret
+            | TokenType::LessLess
+            | TokenType::GreaterGreater => {
+                bitwise(integer_operand(x, op)?, op, integer_operand(y, op)?)
             }
-            Grouping(expression) => format!("(group {})", expression),
-            Variable(name) => name.to_owned(),
-            Assign(_, expression) => expression.to_string(),
-            Logical { left, op, right } => format!("{} {} {}", left, op.lexeme, right),
-            Call { callee, arguments } => format!("{}({:?})", callee, arguments),
-            Get { name, object } => format!("get: {}.{}", object, name),
+            op => Err(RuntimeError::build(format!(
+                "unsupported operation between numbers: `{:?}`",
+                op
+            ))),
+        },
+        (Str(s1), op, Str(s2)) => match &op {
+            TokenType::Plus => {
+                env.check_string_len(s1.len() + s2.len())?;
+                Ok(Str(s1 + &s2))
+            }
+            TokenType::Greater => Ok(Bool(s1 > s2)),
+            TokenType::GreaterEqual => Ok(Bool(s1 >= s2)),
+            TokenType::Less => Ok(Bool(s1 < s2)),
+            TokenType::LessEqual => Ok(Bool(s1 <= s2)),
+            op => Err(RuntimeError::build(format!(
+                "unsupported operation between strings: `{:?}`",
+                op
+            ))),
+        },
+        (Str(s1), TokenType::Plus, right) if right != Nil => {
+            let s2 = right.display(env)?;
+            env.check_string_len(s1.len() + s2.len())?;
+            Ok(Str(s1 + &s2))
+        }
+        (left, TokenType::Plus, Str(s2)) if left != Nil => {
+            let s1 = left.display(env)?;
+            env.check_string_len(s1.len() + s2.len())?;
+            Ok(Str(s1 + &s2))
+        }
+        (Str(s), TokenType::Star, Number(n)) | (Number(n), TokenType::Star, Str(s)) => {
+            if n.fract() != 0.0 || n < 0.0 {
+                return Err(RuntimeError::build(format!(
+                    "can't repeat a string `{n}` times"
+                )));
+            }
+            let Some(len) = s.len().checked_mul(n as usize) else {
+                return Err(RuntimeError::build(format!(
+                    "can't repeat a string `{n}` times: the result is too long"
+                )));
+            };
+            env.check_string_len(len)?;
+            Ok(Str(s.repeat(n as usize)))
+        }
+        _ => Err(RuntimeError::build(
+            "can't evaluate expression: unsupported operation between types".to_string(),
+        )),
+    }
+}
+
+/// Reads the property `name` of an object, calling its getter if it has one.
+fn get(object: Object, name: &str, env: &mut Environment) -> LoxResult<Object> {
+    use Object::*;
+    match object {
+        Callable(f) => {
+            let getter = f.borrow().getter(name)?;
+            match getter {
+                Some(getter) => getter.borrow().call(vec![], env),
+                None => f.borrow().get(name),
+            }
+        }
+        Str(s) => string_method(name)
+            .map(|method| BoundMethod::new(Str(s), method))
+            .map(|method| Callable(Rc::new(RefCell::new(method))))
+            .ok_or(RuntimeError::build(format!(
+                "string has no method `{name}`"
+            ))),
+        _ => Err(RuntimeError::build(format!(
+            "{name} is not is not callable"
+        ))),
+    }
+}
+
+fn get_index(object: Object, index: Object) -> LoxResult<Object> {
+    use Object::*;
+    match (object, index) {
+        (Bytes(bytes), Number(i)) => {
+            let bytes = bytes.borrow();
+            let i = checked_index(i, bytes.len())?;
+            Ok(Number(bytes[i] as f64))
+        }
+        (Array(array), Number(i)) => {
+            let array = array.borrow();
+            let i = checked_index(i, array.len())?;
+            Ok(array[i].clone())
+        }
+        (Str(s), Number(i)) => {
+            let i = checked_index(i, s.chars().count())?;
+            Ok(Str(s.chars().nth(i).unwrap().to_string()))
+        }
+        (Map(map), Str(key)) => map
+            .borrow()
+            .get(&key)
+            .cloned()
+            .ok_or(RuntimeError::build(format!("key {key:?} not found in map"))),
+        (Bytes(_) | Array(_) | Str(_), index) => Err(RuntimeError::build(format!(
+            "expected a number as index but got {}",
+            index.r#type()
+        ))),
+        (Map(_), key) => Err(RuntimeError::build(format!(
+            "expected a string as key but got {}",
+            key.r#type()
+        ))),
+        (object, _) => Err(RuntimeError::build(format!(
+            "{} is not indexable",
+            object.r#type()
+        ))),
+    }
+}
+
+fn set_index(object: Object, index: Object, value: Object) -> LoxResult<Object> {
+    use Object::*;
+    match (object, index) {
+        (Array(array), Number(i)) => {
+            let mut array = array.borrow_mut();
+            let i = checked_index(i, array.len())?;
+            array[i] = value.clone();
+        }
+        (Map(map), Str(key)) => {
+            map.borrow_mut().insert(key, value.clone());
+        }
+        (Array(_), index) => {
+            return Err(RuntimeError::build(format!(
+                "expected a number as index but got {}",
+                index.r#type()
+            )))
+        }
+        (Map(_), key) => {
+            return Err(RuntimeError::build(format!(
+                "expected a string as key but got {}",
+                key.r#type()
+            )))
+        }
+        (object, _) => {
+            return Err(RuntimeError::build(format!(
+                "{} does not support item assignment",
+                object.r#type()
+            )))
+        }
+    }
+    Ok(value)
+}
+
+fn slice(object: Object, start: Object, end: Object) -> LoxResult<Object> {
+    use Object::*;
+    let (Number(start), Number(end)) = (start, end) else {
+        return Err(RuntimeError::build(
+            "expected numbers as slice bounds".to_string(),
+        ));
+    };
+    match object {
+        Str(s) => {
+            let range = checked_range(start, end, s.chars().count())?;
+            let len = range.len();
+            Ok(Str(s.chars().skip(range.start).take(len).collect()))
+        }
+        Array(array) => {
+            let array = array.borrow();
+            let range = checked_range(start, end, array.len())?;
+            Ok(Array(Rc::new(RefCell::new(array[range].to_vec()))))
+        }
+        object => Err(RuntimeError::build(format!(
+            "{} cannot be sliced",
+            object.r#type()
+        ))),
+    }
+}
+
+fn map(entries: &[(Expression, Expression)], env: &mut Environment) -> LoxResult<Object> {
+    let mut map = HashMap::new();
+    for (key, value) in entries {
+        match key.evaluate(env)? {
+            Object::Str(key) => {
+                map.insert(key, value.evaluate(env)?);
+            }
+            key => {
+                return Err(RuntimeError::build(format!(
+                    "expected a string as key but got {}",
+                    key.r#type()
+                )))
+            }
+        }
+    }
+    Ok(Object::Map(Rc::new(RefCell::new(map))))
+}
+
+/// Runs the statements of a block expression in a new scope and evaluates its
+/// value.
+fn block(statements: &[Stmt], value: &Expression, env: &mut Environment) -> LoxResult<Object> {
+    let interpreter = env.interpreter();
+    env.enter_block();
+    let value = statements
+        .iter()
+        .try_for_each(|statement| match interpreter.execute(statement, env)? {
+            None => Ok(()),
+            Some(_) => Err(RuntimeError::build(
+                "`return`, `break` and `continue` cannot leave a block expression".to_string(),
+            )),
+        })
+        .and_then(|()| value.evaluate(env));
+    env.exit_block();
+    value
+}
+
+fn checked_index(i: f64, len: usize) -> LoxResult<usize> {
+    if i.fract() != 0.0 || i < 0.0 || i as usize >= len {
+        Err(RuntimeError::build(format!(
+            "index `{i}` out of range for length {len}"
+        )))
+    } else {
+        Ok(i as usize)
+    }
+}
+
+fn checked_range(start: f64, end: f64, len: usize) -> LoxResult<Range<usize>> {
+    if start.fract() != 0.0 || end.fract() != 0.0 || start < 0.0 || end as usize > len {
+        Err(RuntimeError::build(format!(
+            "slice `{start}:{end}` out of range for length {len}"
+        )))
+    } else if start > end {
+        Err(RuntimeError::build(format!(
+            "slice start `{start}` is after its end `{end}`"
+        )))
+    } else {
+        Ok(start as usize..end as usize)
+    }
+}
+
+/// Calls the method of `left` overloading `op`, such as `add` for `+`, with `right`
+/// as its argument. Returns `None` unless both operands are instances and the left
+/// one has such a method.
+fn overloaded_operator(
+    left: &Object,
+    op: &Token,
+    right: &Object,
+    env: &mut Environment,
+) -> LoxResult<Option<Object>> {
+    let name = match op.r#type {
+        TokenType::Plus => "add",
+        TokenType::Minus => "sub",
+        TokenType::Star => "mul",
+        TokenType::Slash => "div",
+        _ => return Ok(None),
+    };
+    let (Object::Callable(instance), Object::Callable(other)) = (left, right) else {
+        return Ok(None);
+    };
+    let is_instance = |f: &CallableRef| matches!(f.borrow().r#type(), CallableType::Instance);
+    if !is_instance(instance) || !is_instance(other) {
+        return Ok(None);
+    }
+    let Ok(Object::Callable(method)) = instance.borrow().get(name) else {
+        return Ok(None);
+    };
+    let method = method.borrow();
+    if method.arity() > 1 || method.max_arity() < 1 {
+        return Err(RuntimeError::build(format!(
+            "`{name}` must take 1 argument to overload `{}`",
+            op.lexeme
+        )));
+    }
+    method.call(vec![right.clone()], env).map(Some)
+}
+
+/// Whether `object` is an instance of `class`, which must be a class.
+fn instance_of(object: &Object, class: &Object) -> LoxResult<bool> {
+    let class = match class {
+        Object::Callable(class) if matches!(class.borrow().r#type(), CallableType::Class) => class,
+        _ => {
+            return Err(RuntimeError::build(format!(
+                "right operand of `instanceof` must be a class but got {}",
+                class.type_name()
+            )))
+        }
+    };
+    Ok(match object {
+        Object::Callable(object) => {
+            let object = object.borrow();
+            matches!(object.r#type(), CallableType::Instance)
+                && object.class_id() == class.borrow().class_id()
+        }
+        _ => false,
+    })
+}
+
+/// Evaluates the division-like operation `op`, all of which fail on a zero divisor.
+fn divide(x: f64, y: f64, op: &Token) -> LoxResult<Object> {
+    if y == 0.0 {
+        return Err(
+            RuntimeError::build(format!("division by zero in `{}`", op.lexeme)).at_line(op.line),
+        );
+    }
+    match op.r#type {
+        TokenType::Slash => finite(x / y, op),
+        TokenType::Percent => finite(x % y, op),
+        _ => unreachable!("`{}` is not a division", op.lexeme),
+    }
+}
+
+/// The result of an arithmetic operation, which cannot overflow to an infinity:
+/// numbers are always finite, so that they never compare as NaN does.
+fn finite(x: f64, op: &Token) -> LoxResult<Object> {
+    if x.is_finite() {
+        Ok(Object::Number(x))
+    } else {
+        Err(RuntimeError::build(format!(
+            "`{}` overflowed: the result is not a finite number",
+            op.lexeme
+        ))
+        .at_line(op.line))
+    }
+}
+
+fn integer_operand(x: f64, op: &Token) -> LoxResult<i64> {
+    Object::Number(x).as_integer().ok_or_else(|| {
+        RuntimeError::build(format!(
+            "bitwise operator `{}` expects integer operands but got `{x}`",
+            op.lexeme
+        ))
+    })
+}
+
+fn bitwise(x: i64, op: &Token, y: i64) -> LoxResult<Object> {
+    let res = match op.r#type {
+        TokenType::Ampersand => x & y,
+        TokenType::Pipe => x | y,
+        TokenType::Caret => x ^ y,
+        TokenType::LessLess | TokenType::GreaterGreater => {
+            let shift = u32::try_from(y)
+                .ok()
+                .filter(|shift| *shift < i64::BITS)
+                .ok_or(RuntimeError::build(format!("invalid shift amount: `{y}`")))?;
+            if op.r#type == TokenType::LessLess {
+                x << shift
+            } else {
+                x >> shift
+            }
+        }
+        _ => unreachable!("not a bitwise operator"),
+    };
+    Ok(Object::Number(res as f64))
+}
+
+impl Expression {
+    /// Fully parenthesized prefix form of the expression: `1 + 2 * x` gives
+    /// `(+ 1 (* 2 x))`.
+    pub fn repr(&self) -> String {
+        fn list<'a>(expressions: impl IntoIterator<Item = &'a Expression>) -> String {
+            expressions
+                .into_iter()
+                .map(|e| format!(" {}", e.repr()))
+                .collect()
+        }
+
+        match self {
+            Literal(object) => object.literal(),
+            Unary { op, right } => format!("({} {})", op.lexeme, right.repr()),
+            Binary { left, op, right } | Logical { left, op, right } => {
+                format!("({} {} {})", op.lexeme, left.repr(), right.repr())
+            }
+            Grouping(expression) => format!("(group {})", expression.repr()),
+            Variable { name, .. } => name.to_string(),
+            Assign(name, value) => format!("(= {} {})", name, value.repr()),
+            PostfixUpdate { name, op, .. } => format!("(post{0}{0} {name})", op.lexeme),
+            Call {
+                callee, arguments, ..
+            } => format!("(call {}{})", callee.repr(), list(arguments)),
+            Get { name, object } => format!("(. {} {})", object.repr(), name),
             Set {
                 object,
                 name,
                 value,
-            } => format!("set: {}.{} = {}", object, name, value),
-            This => todo!(),
+            } => format!("(= (. {} {}) {})", object.repr(), name, value.repr()),
+            Index { object, index } => format!("([] {} {})", object.repr(), index.repr()),
+            Slice { object, start, end } => {
+                format!("([:] {} {} {})", object.repr(), start.repr(), end.repr())
+            }
+            SetIndex {
+                object,
+                index,
+                value,
+            } => format!(
+                "(= ([] {} {}) {})",
+                object.repr(),
+                index.repr(),
+                value.repr()
+            ),
+            Array(elements) => format!("(array{})", list(elements)),
+            Map(entries) => format!(
+                "(map{})",
+                list(entries.iter().flat_map(|(key, value)| [key, value]))
+            ),
+            Interpolation(parts) => format!("(interpolation{})", list(parts)),
+            This => "this".to_string(),
+            Block(statements, value) => {
+                let mut parts = statement_list(statements);
+                parts.push(value.repr());
+                format!("(block {})", parts.join(" "))
+            }
+        }
+    }
+
+    /// Reverse Polish form of the expression: `1 + 2 * x` gives `1 2 x * +`.
+    /// Calls, arrays and maps are suffixed with their number of operands
+    /// (`f(1, 2)` gives `f 1 2 call/2`).
+    pub fn rpn(&self) -> String {
+        fn list<'a>(expressions: impl IntoIterator<Item = &'a Expression>) -> String {
+            expressions
+                .into_iter()
+                .map(|e| format!("{} ", e.rpn()))
+                .collect()
+        }
+
+        match self {
+            Literal(object) => object.literal(),
+            Unary { op, right } => format!("{} {}", right.rpn(), op.lexeme),
+            Binary { left, op, right } | Logical { left, op, right } => {
+                format!("{} {} {}", left.rpn(), right.rpn(), op.lexeme)
+            }
+            Grouping(expression) => expression.rpn(),
+            Variable { name, .. } => name.to_string(),
+            Assign(name, value) => format!("{} {} =", value.rpn(), name),
+            PostfixUpdate { name, op, .. } => format!("{name} post{0}{0}", op.lexeme),
+            Call {
+                callee, arguments, ..
+            } => format!(
+                "{} {}call/{}",
+                callee.rpn(),
+                list(arguments),
+                arguments.len()
+            ),
+            Get { name, object } => format!("{} .{}", object.rpn(), name),
+            Set {
+                object,
+                name,
+                value,
+            } => format!("{} {} .{}=", object.rpn(), value.rpn(), name),
+            Index { object, index } => format!("{} {} []", object.rpn(), index.rpn()),
+            Slice { object, start, end } => {
+                format!("{} {} {} [:]", object.rpn(), start.rpn(), end.rpn())
+            }
+            SetIndex {
+                object,
+                index,
+                value,
+            } => format!("{} {} {} []=", object.rpn(), index.rpn(), value.rpn()),
+            Array(elements) => format!("{}array/{}", list(elements), elements.len()),
+            Map(entries) => format!(
+                "{}map/{}",
+                list(entries.iter().flat_map(|(key, value)| [key, value])),
+                entries.len()
+            ),
+            Interpolation(parts) => format!("{}interpolation/{}", list(parts), parts.len()),
+            This => "this".to_string(),
+            Block(statements, value) => {
+                let mut parts = statement_list(statements);
+                parts.push(value.rpn());
+                format!("{} block/{}", parts.join(" "), parts.len())
+            }
+        }
+    }
+}
+
+/// The statements of a block expression, each as its tree on a single line.
+fn statement_list(statements: &[Stmt]) -> Vec<String> {
+    statements
+        .iter()
+        .map(|s| {
+            let tree = s.tree();
+            let lines: Vec<_> = tree.lines().map(str::trim).collect();
+            format!("[{}]", lines.join(" "))
+        })
+        .collect()
+}
+
+/// Evaluates the callee and the arguments of a call, after checking that the
+/// callee accepts that many arguments.
+pub fn prepare_call(
+    callee: &Expression,
+    arguments: &[Expression],
+    env: &mut Environment,
+) -> LoxResult<(CallableRef, Vec<Object>)> {
+    let name = callee.to_string();
+    let Object::Callable(f) = callee.evaluate(env)? else {
+        return Err(RuntimeError::build(format!(
+            "{name} is not is not callable"
+        )));
+    };
+    let arity = f.borrow().arity();
+    let max_arity = f.borrow().max_arity();
+    let num_args = arguments.len();
+    if num_args < arity || num_args > max_arity {
+        let (expected, plural) = if arity == max_arity {
+            (arity.to_string(), arity > 1)
+        } else if max_arity == usize::MAX {
+            (format!("at least {arity}"), arity > 1)
+        } else {
+            (format!("{arity} to {max_arity}"), true)
         };
-        write!(f, "{s}")
+        return Err(RuntimeError::build(format!(
+            "`{}`: expected {expected} argument{} but got {num_args}",
+            callable_repr(&*f.borrow()),
+            if plural { "s" } else { "" },
+        )));
+    }
+    let objects = arguments
+        .iter()
+        .map(|arg| arg.evaluate(env))
+        .collect::<Result<Vec<_>, _>>()?;
+    if env.trace() {
+        let arguments = objects.iter().map(Object::literal).collect::<Vec<_>>();
+        eprintln!(
+            "[trace] call {}({})",
+            f.borrow().name(),
+            arguments.join(", ")
+        );
+    }
+    Ok((f, objects))
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.repr())
     }
 }
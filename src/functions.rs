@@ -1,14 +1,18 @@
 use crate::errors::{LoxError, LoxResult, RuntimeError};
-use crate::expression::Object;
-use crate::interpreter::{Environment, Interpreter, Signal};
+use crate::expression::{Expression, Object};
+use crate::interpreter::{Environment, Signal};
 use crate::parser::Stmt;
+use crate::symbol::Symbol;
 use rand::{thread_rng, Rng};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
-use std::process;
-use std::rc::Rc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::io::{self, Write};
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs};
 
 pub enum CallableType {
     Function,
@@ -26,11 +30,30 @@ impl Display for CallableType {
     }
 }
 
+/// Shared handle to a callable, as held by `Object::Callable`.
+pub type CallableRef = Rc<RefCell<dyn Callable>>;
+
 pub trait Callable {
     fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object>;
 
+    /// Calls a function whose own name refers to `itself` in its body, so that
+    /// its recursive calls go through a wrapper such as the one of `limit_calls`.
+    fn call_as(
+        &self,
+        objects: Vec<Object>,
+        env: &mut Environment,
+        _itself: CallableRef,
+    ) -> LoxResult<Object> {
+        self.call(objects, env)
+    }
+
     fn arity(&self) -> usize;
 
+    /// Maximum number of arguments, for callables taking optional trailing arguments.
+    fn max_arity(&self) -> usize {
+        self.arity()
+    }
+
     fn name(&self) -> &str;
 
     fn doc(&self) -> &str {
@@ -39,6 +62,12 @@ pub trait Callable {
 
     fn r#type(&self) -> CallableType;
 
+    /// Whether calling this has no side effect, so that a call whose result is unused
+    /// can be dropped and a call on constant arguments can be folded.
+    fn is_pure(&self) -> bool {
+        false
+    }
+
     fn get(&self, _name: &str) -> LoxResult<Object> {
         Err(RuntimeError::build(
             "only instances have porperties".to_string(),
@@ -50,6 +79,27 @@ pub trait Callable {
             "only instances can set porperties".to_string(),
         ))
     }
+
+    /// The fields of an instance, `None` for anything else.
+    fn fields(&self) -> Option<HashMap<String, Object>> {
+        None
+    }
+
+    /// The getter called to read the property `name`, bound to the instance.
+    fn getter(&self, _name: &str) -> LoxResult<Option<CallableRef>> {
+        Ok(None)
+    }
+
+    /// Whether an instance has a field, a method or a getter named `name`.
+    fn has_property(&self, _name: &str) -> bool {
+        false
+    }
+
+    /// The identity of a class, or of the class of an instance, which tells apart
+    /// classes of the same name.
+    fn class_id(&self) -> Option<usize> {
+        None
+    }
 }
 
 pub struct Exit;
@@ -58,9 +108,8 @@ impl Callable for Exit {
     fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
         let value = objects.first().expect("expected one argument");
         if let Object::Number(x) = value {
-            if x.fract() == 0.0 {
-                let exit_code = *x as i32;
-                process::exit(exit_code);
+            if value.is_integer() {
+                return Err(LoxError::Exit(*x as i32));
             }
         }
         Err(RuntimeError::build(format!(
@@ -78,7 +127,7 @@ impl Callable for Exit {
     }
 
     fn doc(&self) -> &str {
-        "Terminates the current process with the specified exit code."
+        "Stops the program with the specified exit code."
     }
 
     fn r#type(&self) -> CallableType {
@@ -90,7 +139,7 @@ pub struct Quit;
 
 impl Callable for Quit {
     fn call(&self, _objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
-        process::exit(0);
+        Err(LoxError::Exit(0))
     }
 
     fn arity(&self) -> usize {
@@ -102,7 +151,78 @@ impl Callable for Quit {
     }
 
     fn doc(&self) -> &str {
-        "Terminates the current process with an exit code of 0."
+        "Stops the program with an exit code of 0."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct Assert;
+
+impl Callable for Assert {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let mut iter = objects.into_iter();
+        let value = iter.next().expect("expected one argument");
+        if value.into() {
+            return Ok(Object::Nil);
+        }
+        match iter.next() {
+            Some(message) => Err(RuntimeError::build(format!("assertion failed: {message}"))),
+            None => Err(RuntimeError::build("assertion failed".to_string())),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn max_arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "assert"
+    }
+
+    fn doc(&self) -> &str {
+        "Raises an error, with an optional message, if the given object is falsy."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct AssertEq;
+
+impl Callable for AssertEq {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let [left, right] = &objects[..] else {
+            unreachable!("expected two arguments");
+        };
+        if left == right {
+            Ok(Object::Nil)
+        } else {
+            Err(RuntimeError::build(format!(
+                "assertion failed: {} != {}",
+                left.literal(),
+                right.literal()
+            )))
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "assert_eq"
+    }
+
+    fn doc(&self) -> &str {
+        "Raises an error showing both values if they are not equal."
     }
 
     fn r#type(&self) -> CallableType {
@@ -221,17 +341,18 @@ impl Callable for Randint {
         let mut iter = objects.into_iter();
         let low = iter.next().expect("expected low bound");
         let high = iter.next().expect("expected high bound");
-        if let (Object::Number(low), Object::Number(high)) = (low, high) {
-            if low.fract() == 0.0 && high.fract() == 0.0 {
-                let low = low as i32;
-                let high = high as i32;
-                let rand = thread_rng().gen_range(low..=high);
-                return Ok(Object::Number(rand as f64));
-            }
+        let (Some(low), Some(high)) = (low.as_integer(), high.as_integer()) else {
+            return Err(RuntimeError::build(
+                "randint: expected two integers arguments".to_string(),
+            ));
+        };
+        if low > high {
+            return Err(RuntimeError::build(format!(
+                "randint: low bound {low} is greater than high bound {high}"
+            )));
         }
-        Err(RuntimeError::build(
-            "randint: expected two integers arguments".to_string(),
-        ))
+        let rand = thread_rng().gen_range(low..=high);
+        Ok(Object::Number(rand as f64))
     }
 
     fn arity(&self) -> usize {
@@ -250,6 +371,9 @@ impl Callable for Randint {
     }
 }
 
+/// Largest precision of `round`, whose power of ten is still exact as a float.
+const MAX_ROUND_PRECISION: i64 = 22;
+
 pub struct Round;
 
 impl Callable for Round {
@@ -257,22 +381,30 @@ impl Callable for Round {
         let mut iter = objects.into_iter();
         let value = iter.next().expect("expected a number");
         let precision = iter.next().expect("expected an integer");
-        if let (Object::Number(value), Object::Number(precision)) = (value, precision) {
-            if precision.fract() == 0.0 {
-                let precision = precision as u32;
-                let pow = u32::pow(10, precision) as f64;
-                let res = (value * pow).round() / pow;
-                Ok(Object::Number(res))
-            } else {
-                Err(RuntimeError::build(
-                    "round: precision must be an integer".to_string(),
-                ))
-            }
-        } else {
-            Err(RuntimeError::build(
+        let (Object::Number(value), Object::Number(_)) = (value, &precision) else {
+            return Err(RuntimeError::build(
                 "round: arguments one number and one integer".to_string(),
-            ))
+            ));
+        };
+        let Some(precision) = precision.as_integer() else {
+            return Err(RuntimeError::build(
+                "round: precision must be an integer".to_string(),
+            ));
+        };
+        if !(0..=MAX_ROUND_PRECISION).contains(&precision) {
+            return Err(RuntimeError::build(format!(
+                "round: precision must be between 0 and {MAX_ROUND_PRECISION} but got {precision}"
+            )));
         }
+        let pow = 10f64.powi(precision as i32);
+        let scaled = value * pow;
+        // a number too large to scale has no decimal digit left to round
+        let res = if scaled.is_finite() {
+            scaled.round() / pow
+        } else {
+            value
+        };
+        Ok(Object::Number(res))
     }
 
     fn arity(&self) -> usize {
@@ -289,15 +421,47 @@ impl Callable for Round {
     fn r#type(&self) -> CallableType {
         CallableType::Function
     }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
 }
 
-pub struct Print;
+pub struct GroupDigits;
 
-impl Callable for Print {
+impl Callable for GroupDigits {
     fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
         let value = objects.first().expect("expected one argument");
-        println!("{value}");
-        Ok(Object::Nil)
+        if let Object::Number(x) = value {
+            if !x.is_finite() {
+                return Ok(Object::Str(x.to_string()));
+            }
+            let repr = x.abs().to_string();
+            let (int_part, frac_part) = match repr.split_once('.') {
+                Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+                None => (repr.as_str(), None),
+            };
+            let mut grouped = String::new();
+            if x.is_sign_negative() && *x != 0.0 {
+                grouped.push('-');
+            }
+            for (i, c) in int_part.chars().enumerate() {
+                if i > 0 && (int_part.len() - i) % 3 == 0 {
+                    grouped.push(',');
+                }
+                grouped.push(c);
+            }
+            if let Some(frac_part) = frac_part {
+                grouped.push('.');
+                grouped.push_str(frac_part);
+            }
+            Ok(Object::Str(grouped))
+        } else {
+            Err(RuntimeError::build(format!(
+                "{}: expected a number",
+                self.name()
+            )))
+        }
     }
 
     fn arity(&self) -> usize {
@@ -305,107 +469,114 @@ impl Callable for Print {
     }
 
     fn name(&self) -> &str {
-        "print"
+        "group_digits"
     }
 
     fn doc(&self) -> &str {
-        "Prints its argument to the standard output, with a newline."
+        "Returns the number as a string with its digits grouped by thousands."
     }
 
     fn r#type(&self) -> CallableType {
         CallableType::Function
     }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
 }
 
-pub struct Dir;
+pub struct Repr;
 
-impl Callable for Dir {
-    fn call(&self, _objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
-        env.last_mut()
-            .iter()
-            .for_each(|(name, _)| println!("{name}"));
-        Ok(Object::Nil)
+impl Callable for Repr {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        Ok(Object::Str(value.repr()))
     }
 
     fn arity(&self) -> usize {
-        0
+        1
     }
 
     fn name(&self) -> &str {
-        "dir"
+        "repr"
     }
 
     fn doc(&self) -> &str {
-        "Prints all the names in the current scope."
+        "Returns the unambiguous representation of the given object (`2.0`, `\"abc\"`)."
     }
 
     fn r#type(&self) -> CallableType {
         CallableType::Function
     }
-}
 
-#[derive(Clone)]
-pub struct UserDefinedFunction {
-    name: String,
-    body: Vec<Stmt>,
-    parameters: Vec<String>,
-    closure: Environment,
+    fn is_pure(&self) -> bool {
+        true
+    }
 }
 
-impl UserDefinedFunction {
-    pub fn new(
-        name: String,
-        body: Vec<Stmt>,
-        parameters: Vec<String>,
-        closure: Environment,
-    ) -> Self {
-        Self {
-            name,
-            body,
-            parameters,
-            closure,
+pub struct ReadBytes;
+
+impl Callable for ReadBytes {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        if let Object::Str(path) = value {
+            fs::read(path)
+                .map(|bytes| Object::Bytes(Rc::new(RefCell::new(bytes))))
+                .map_err(|why| RuntimeError::build(format!("cannot read {path}: {why}")))
+        } else {
+            Err(RuntimeError::build(format!(
+                "{}: expected a path",
+                self.name()
+            )))
         }
     }
 
-    fn bind(&self, obj: &Instance) -> Self {
-        let mut method = self.clone();
-        for (name, object) in &obj.fields {
-            method.closure.define(name, Some(object.clone()));
-        }
-        method.closure.define(
-            // this should be a reference to the object, not a copy of it
-            "this",
-            Some(Object::Callable(Rc::new(RefCell::new(obj.clone())))),
-        );
-        method
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "read_bytes"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the content of the given file as bytes."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
     }
 }
 
-impl Callable for UserDefinedFunction {
+pub struct WriteBytes;
+
+impl Callable for WriteBytes {
     fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
-        let mut env = self.closure.clone();
-        env.define(
-            &self.name,
-            Some(Object::Callable(Rc::new(RefCell::new(self.clone())))),
-        );
-        self.parameters
-            .iter()
-            .zip(objects)
-            .for_each(|(param, value)| env.define(param, Some(value)));
-        let interpreter = Interpreter::new();
-        if let Some(Signal::Return(Some(expr))) = interpreter.interpret(&mut env, &self.body)? {
-            expr.evaluate(&mut env)
+        let mut iter = objects.into_iter();
+        let path = iter.next().expect("expected a path");
+        let bytes = iter.next().expect("expected bytes");
+        if let (Object::Str(path), Object::Bytes(bytes)) = (path, bytes) {
+            fs::write(&path, bytes.borrow().as_slice())
+                .map(|_| Object::Nil)
+                .map_err(|why| RuntimeError::build(format!("cannot write {path}: {why}")))
         } else {
-            Ok(Object::Nil)
+            Err(RuntimeError::build(format!(
+                "{}: expected a path and bytes",
+                self.name()
+            )))
         }
     }
 
     fn arity(&self) -> usize {
-        self.parameters.len()
+        2
     }
 
     fn name(&self) -> &str {
-        &self.name
+        "write_bytes"
+    }
+
+    fn doc(&self) -> &str {
+        "Writes the given bytes to a file, replacing its content."
     }
 
     fn r#type(&self) -> CallableType {
@@ -413,103 +584,2038 @@ impl Callable for UserDefinedFunction {
     }
 }
 
-#[derive(Clone)]
-pub struct UserDefinedStruct {
-    name: String,
-    methods: HashMap<String, UserDefinedFunction>,
-}
+pub struct Len;
 
-impl UserDefinedStruct {
-    pub fn new(name: String, methods: HashMap<String, UserDefinedFunction>) -> Self {
-        Self { name, methods }
+impl Callable for Len {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        match value {
+            Object::Str(s) => Ok(Object::Number(s.chars().count() as f64)),
+            Object::Bytes(bytes) => Ok(Object::Number(bytes.borrow().len() as f64)),
+            Object::Array(array) => Ok(Object::Number(array.borrow().len() as f64)),
+            Object::Map(map) => Ok(Object::Number(map.borrow().len() as f64)),
+            _ => Err(RuntimeError::build(format!(
+                "{}: {} has no length",
+                self.name(),
+                value.r#type()
+            ))),
+        }
     }
 
-    fn find_method(&self, name: &str) -> Option<UserDefinedFunction> {
-        self.methods.get(name).cloned()
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "len"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the length of a string, bytes, an array or a map."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
     }
 }
 
-impl Callable for UserDefinedStruct {
-    fn call(&self, _objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
-        Ok(Object::Callable(Rc::new(RefCell::new(Instance::new(
-            self.clone(),
-        )))))
+pub struct Upper;
+
+impl Callable for Upper {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        if let Object::Str(s) = value {
+            Ok(Object::Str(s.to_uppercase()))
+        } else {
+            Err(RuntimeError::build(format!(
+                "{}: expected a string",
+                self.name()
+            )))
+        }
     }
 
     fn arity(&self) -> usize {
-        0
+        1
     }
 
     fn name(&self) -> &str {
-        &self.name
+        "upper"
     }
 
     fn doc(&self) -> &str {
-        "No documentation available."
+        "Returns a copy of the string converted to uppercase."
     }
 
     fn r#type(&self) -> CallableType {
-        CallableType::Class
+        CallableType::Function
     }
-}
-
-#[derive(Clone)]
-pub struct Instance {
-    base: UserDefinedStruct,
-    fields: HashMap<String, Object>,
-}
 
-impl Instance {
-    pub fn new(base: UserDefinedStruct) -> Instance {
-        Self {
-            base,
-            fields: HashMap::new(),
-        }
+    fn is_pure(&self) -> bool {
+        true
     }
 }
 
-impl Callable for Instance {
-    fn call(&self, _objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
-        todo!();
+pub struct Lower;
+
+impl Callable for Lower {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        if let Object::Str(s) = value {
+            Ok(Object::Str(s.to_lowercase()))
+        } else {
+            Err(RuntimeError::build(format!(
+                "{}: expected a string",
+                self.name()
+            )))
+        }
     }
 
     fn arity(&self) -> usize {
-        0
+        1
     }
+
     fn name(&self) -> &str {
-        self.base.name()
+        "lower"
     }
 
     fn doc(&self) -> &str {
-        "A class instance."
+        "Returns a copy of the string converted to lowercase."
     }
 
     fn r#type(&self) -> CallableType {
-        CallableType::Instance
+        CallableType::Function
     }
 
-    fn get(&self, name: &str) -> LoxResult<Object> {
-        if let Some(obj) = self.fields.get(name) {
-            Ok(obj.clone())
-        } else if let Some(method) = self.base.find_method(name) {
-            let method = method.bind(self);
-            Ok(Object::Callable(Rc::new(RefCell::new(method))))
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Trim;
+
+impl Callable for Trim {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        if let Object::Str(s) = value {
+            Ok(Object::Str(s.trim().to_string()))
         } else {
             Err(RuntimeError::build(format!(
-                "undefined property `{}`",
-                name
+                "{}: expected a string",
+                self.name()
             )))
         }
     }
 
-    fn set(&mut self, name: &str, value: Object) -> LoxResult<()> {
-        self.fields.insert(name.to_owned(), value);
-        Ok(())
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "trim"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns a copy of the string without leading and trailing whitespace."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
     }
 }
 
-impl Display for dyn Callable {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "<{} {}>", self.r#type(), self.name())
+pub struct IsInteger;
+
+impl Callable for IsInteger {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        Ok(Object::Bool(value.is_integer()))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "is_integer"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns true if the given object is a whole number."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Split;
+
+impl Callable for Split {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let mut iter = objects.into_iter();
+        let value = iter.next().expect("expected a string");
+        let separator = iter.next().expect("expected a separator");
+        match (value, separator) {
+            (Object::Str(_), Object::Str(separator)) if separator.is_empty() => Err(
+                RuntimeError::build(format!("{}: empty separator", self.name())),
+            ),
+            (Object::Str(s), Object::Str(separator)) => {
+                let parts = s
+                    .split(separator.as_str())
+                    .map(|part| Object::Str(part.to_string()))
+                    .collect();
+                Ok(Object::Array(Rc::new(RefCell::new(parts))))
+            }
+            _ => Err(RuntimeError::build(format!(
+                "{}: expected two strings",
+                self.name()
+            ))),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "split"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns an array of the parts of a string separated by the given separator."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Join;
+
+impl Callable for Join {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let mut iter = objects.into_iter();
+        let array = iter.next().expect("expected an array");
+        let separator = iter.next().expect("expected a separator");
+        if let (Object::Array(array), Object::Str(separator)) = (array, separator) {
+            let parts = array
+                .borrow()
+                .iter()
+                .map(|object| object.to_string())
+                .collect::<Vec<_>>();
+            let len = parts.iter().map(String::len).sum::<usize>()
+                + separator.len() * parts.len().saturating_sub(1);
+            env.check_string_len(len)?;
+            Ok(Object::Str(parts.join(&separator)))
+        } else {
+            Err(RuntimeError::build(format!(
+                "{}: expected an array and a string",
+                self.name()
+            )))
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "join"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the elements of an array joined into a string by the given separator."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Range;
+
+/// The most elements `range` builds, well below what fits in memory.
+const MAX_RANGE_LEN: usize = 10_000_000;
+
+impl Callable for Range {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let mut numbers = vec![];
+        for object in objects {
+            if let Object::Number(x) = object {
+                numbers.push(x);
+            } else {
+                return Err(RuntimeError::build(format!(
+                    "{}: expected numbers",
+                    self.name()
+                )));
+            }
+        }
+        let (start, stop, step) = match numbers[..] {
+            [stop] => (0.0, stop, 1.0),
+            [start, stop] => (start, stop, 1.0),
+            [start, stop, step] => (start, stop, step),
+            _ => unreachable!("arity is checked before the call"),
+        };
+        if step == 0.0 {
+            return Err(RuntimeError::build(format!(
+                "{}: step cannot be zero",
+                self.name()
+            )));
+        }
+        let len = ((stop - start) / step).ceil().max(0.0);
+        if len > MAX_RANGE_LEN as f64 {
+            return Err(RuntimeError::build(format!(
+                "{}: range of {len} elements exceeds the maximum of {MAX_RANGE_LEN}",
+                self.name()
+            )));
+        }
+        let mut elements = Vec::with_capacity(len as usize);
+        let mut x = start;
+        while (step > 0.0 && x < stop) || (step < 0.0 && x > stop) {
+            elements.push(Object::Number(x));
+            if x + step == x {
+                return Err(RuntimeError::build(format!(
+                    "{}: step {step} is too small to change {x}",
+                    self.name()
+                )));
+            }
+            x += step;
+        }
+        Ok(Object::Array(Rc::new(RefCell::new(elements))))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn max_arity(&self) -> usize {
+        3
+    }
+
+    fn name(&self) -> &str {
+        "range"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns an array of numbers from start (default 0) up to stop, by step (default 1)."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Keys;
+
+impl Callable for Keys {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        if let Object::Map(map) = value {
+            let mut keys = map.borrow().keys().cloned().collect::<Vec<_>>();
+            keys.sort();
+            let keys = keys.into_iter().map(Object::Str).collect();
+            Ok(Object::Array(Rc::new(RefCell::new(keys))))
+        } else {
+            Err(RuntimeError::build(format!(
+                "{}: expected a map",
+                self.name()
+            )))
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "keys"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the sorted keys of a map."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Values;
+
+impl Callable for Values {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        if let Object::Map(map) = value {
+            let map = map.borrow();
+            let mut keys = map.keys().collect::<Vec<_>>();
+            keys.sort();
+            let values = keys.into_iter().map(|key| map[key].clone()).collect();
+            Ok(Object::Array(Rc::new(RefCell::new(values))))
+        } else {
+            Err(RuntimeError::build(format!(
+                "{}: expected a map",
+                self.name()
+            )))
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "values"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the values of a map, ordered by key."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+/// Returns the position of a substring in a string or of an element in an array.
+fn position(name: &str, objects: Vec<Object>) -> LoxResult<Option<usize>> {
+    let mut iter = objects.into_iter();
+    let haystack = iter.next().expect("expected a string or an array");
+    let needle = iter.next().expect("expected the searched object");
+    match (haystack, needle) {
+        (Object::Str(s), Object::Str(sub)) => Ok(s.find(&sub).map(|i| s[..i].chars().count())),
+        (Object::Array(array), needle) => Ok(array.borrow().iter().position(|o| *o == needle)),
+        _ => Err(RuntimeError::build(format!(
+            "{name}: expected a string and a substring or an array and an element"
+        ))),
+    }
+}
+
+pub struct Contains;
+
+impl Callable for Contains {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let index = position(self.name(), objects)?;
+        Ok(Object::Bool(index.is_some()))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "contains"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns true if a string contains the given substring or an array the given element."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct IndexOf;
+
+impl Callable for IndexOf {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let index = position(self.name(), objects)?;
+        Ok(Object::Number(index.map_or(-1.0, |i| i as f64)))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "index_of"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the index of a substring in a string or of an element in an array, or -1."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct ReadFile;
+
+impl Callable for ReadFile {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        if let Object::Str(path) = value {
+            let content = fs::read_to_string(path)
+                .map_err(|why| RuntimeError::build(format!("cannot read {path}: {why}")))?;
+            env.check_string_len(content.len())?;
+            Ok(Object::Str(content))
+        } else {
+            Err(RuntimeError::build(format!(
+                "{}: expected a path",
+                self.name()
+            )))
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the content of the given file as a string."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct WriteFile;
+
+impl Callable for WriteFile {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let mut iter = objects.into_iter();
+        let path = iter.next().expect("expected a path");
+        let content = iter.next().expect("expected a string");
+        if let (Object::Str(path), Object::Str(content)) = (path, content) {
+            fs::write(&path, content)
+                .map(|_| Object::Nil)
+                .map_err(|why| RuntimeError::build(format!("cannot write {path}: {why}")))
+        } else {
+            Err(RuntimeError::build(format!(
+                "{}: expected a path and a string",
+                self.name()
+            )))
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "write_file"
+    }
+
+    fn doc(&self) -> &str {
+        "Writes the given string to a file, replacing its content."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+/// Wraps a callable so that it errors once it has been invoked `max_calls` times.
+pub struct LimitCalls {
+    name: String,
+    function: Rc<RefCell<dyn Callable>>,
+    max_calls: usize,
+    calls: RefCell<usize>,
+    /// The wrapper itself, which the recursive calls of the function go through.
+    itself: Weak<RefCell<LimitCalls>>,
+}
+
+impl Callable for LimitCalls {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let mut calls = self.calls.borrow_mut();
+        if *calls >= self.max_calls {
+            return Err(RuntimeError::build(format!(
+                "`{}` was called more than {} times",
+                self.name(),
+                self.max_calls
+            )));
+        }
+        *calls += 1;
+        drop(calls);
+        let itself = self.itself.upgrade().expect("the wrapper is being called");
+        self.function.borrow().call_as(objects, env, itself)
+    }
+
+    fn arity(&self) -> usize {
+        self.function.borrow().arity()
+    }
+
+    fn max_arity(&self) -> usize {
+        self.function.borrow().max_arity()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn doc(&self) -> &str {
+        "A function that can only be called a limited number of times."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct LimitCallsBuiltin;
+
+impl Callable for LimitCallsBuiltin {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let mut iter = objects.into_iter();
+        let function = iter.next().expect("expected a function");
+        let max_calls = iter.next().expect("expected a number of calls");
+        match (function, max_calls) {
+            (Object::Callable(function), Object::Number(n)) if n.fract() == 0.0 && n >= 0.0 => {
+                let name = function.borrow().name().to_string();
+                let limited = Rc::new_cyclic(|itself| {
+                    RefCell::new(LimitCalls {
+                        name,
+                        function,
+                        max_calls: n as usize,
+                        calls: RefCell::new(0),
+                        itself: itself.clone(),
+                    })
+                });
+                Ok(Object::Callable(limited))
+            }
+            _ => Err(RuntimeError::build(format!(
+                "{}: expected a function and a number of calls",
+                self.name()
+            ))),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "limit_calls"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns a function that errors once it has been called more than n times."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct Between;
+
+impl Callable for Between {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let result = match &objects[..] {
+            [Object::Number(x), Object::Number(lo), Object::Number(hi)] => lo <= x && x <= hi,
+            [Object::Str(x), Object::Str(lo), Object::Str(hi)] => lo <= x && x <= hi,
+            _ => {
+                return Err(RuntimeError::build(format!(
+                    "{}: expected three numbers or three strings",
+                    self.name()
+                )))
+            }
+        };
+        Ok(Object::Bool(result))
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+
+    fn name(&self) -> &str {
+        "between"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns true if lo <= x <= hi, for numbers or strings."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct GetOr;
+
+impl Callable for GetOr {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let mut objects = objects.into_iter();
+        let (instance, field, default) = (
+            objects.next().expect("expected three arguments"),
+            objects.next().expect("expected three arguments"),
+            objects.next().expect("expected three arguments"),
+        );
+        match (instance, field) {
+            (Object::Callable(c), Object::Str(field))
+                if matches!(c.borrow().r#type(), CallableType::Instance) =>
+            {
+                if !c.borrow().has_property(&field) {
+                    return Ok(default);
+                }
+                let getter = c.borrow().getter(&field)?;
+                match getter {
+                    Some(getter) => getter.borrow().call(vec![], env),
+                    None => c.borrow().get(&field),
+                }
+            }
+            _ => Err(RuntimeError::build(format!(
+                "{}: expected an instance and a field name",
+                self.name()
+            ))),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+
+    fn name(&self) -> &str {
+        "get_or"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the value of a property of an instance, or the default if it has none by that name."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct TypeOf;
+
+impl Callable for TypeOf {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        Ok(Object::Str(value.type_name().to_string()))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "typeof"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the name of the type of the given object."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct DeepEq;
+
+impl Callable for DeepEq {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let [a, b] = &objects[..] else {
+            unreachable!("arity is checked before the call");
+        };
+        Ok(Object::Bool(deep_eq(a, b, &mut HashSet::new())))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "deep_eq"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns true if two values are structurally equal, comparing arrays, maps and instances by content."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+/// Structural equality. `visited` holds the pairs of containers being compared,
+/// so that a cycle is considered equal instead of recursing forever.
+fn deep_eq(a: &Object, b: &Object, visited: &mut HashSet<(usize, usize)>) -> bool {
+    match (a, b) {
+        (Object::Array(a), Object::Array(b)) => {
+            if !visited.insert((Rc::as_ptr(a) as usize, Rc::as_ptr(b) as usize)) {
+                return true;
+            }
+            let (a, b) = (a.borrow(), b.borrow());
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| deep_eq(a, b, visited))
+        }
+        (Object::Map(a), Object::Map(b)) => {
+            if !visited.insert((Rc::as_ptr(a) as usize, Rc::as_ptr(b) as usize)) {
+                return true;
+            }
+            fields_eq(&a.borrow(), &b.borrow(), visited)
+        }
+        (Object::Callable(a), Object::Callable(b)) => {
+            if Rc::ptr_eq(a, b) {
+                return true;
+            }
+            let key = (
+                Rc::as_ptr(a) as *const () as usize,
+                Rc::as_ptr(b) as *const () as usize,
+            );
+            let (a, b) = (a.borrow(), b.borrow());
+            match (a.fields(), b.fields()) {
+                (Some(fields_a), Some(fields_b)) if a.name() == b.name() => {
+                    !visited.insert(key) || fields_eq(&fields_a, &fields_b, visited)
+                }
+                _ => false,
+            }
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn fields_eq(
+    a: &HashMap<String, Object>,
+    b: &HashMap<String, Object>,
+    visited: &mut HashSet<(usize, usize)>,
+) -> bool {
+    a.len() == b.len()
+        && a.iter().all(|(key, value_a)| match b.get(key) {
+            Some(value_b) => deep_eq(value_a, value_b, visited),
+            None => false,
+        })
+}
+
+pub struct Sleep;
+
+impl Callable for Sleep {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        match objects.first().expect("expected one argument") {
+            value @ Object::Number(ms) if value.is_integer() && *ms >= 0.0 => {
+                thread::sleep(Duration::from_millis(*ms as u64));
+                Ok(Object::Nil)
+            }
+            _ => Err(RuntimeError::build(format!(
+                "{}: expected a non-negative integer number of milliseconds",
+                self.name()
+            ))),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "sleep"
+    }
+
+    fn doc(&self) -> &str {
+        "Pauses the program for the given number of milliseconds."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct NumCmp;
+
+impl Callable for NumCmp {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let mut numbers = vec![];
+        for object in objects {
+            let number = match object {
+                Object::Number(x) => Some(x),
+                Object::Str(s) => s.trim().parse::<f64>().ok(),
+                _ => None,
+            };
+            match number {
+                Some(x) if !x.is_nan() => numbers.push(x),
+                _ => {
+                    return Err(RuntimeError::build(format!(
+                        "{}: expected numbers or numeric strings",
+                        self.name()
+                    )))
+                }
+            }
+        }
+        let ordering = numbers[0]
+            .partial_cmp(&numbers[1])
+            .expect("NaN was rejected") as i8;
+        Ok(Object::Number(ordering as f64))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "num_cmp"
+    }
+
+    fn doc(&self) -> &str {
+        "Compares two numbers, or strings parsed as numbers: returns -1, 0 or 1."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Format;
+
+impl Callable for Format {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let mut iter = objects.into_iter();
+        let Object::Str(template) = iter.next().expect("expected a template") else {
+            return Err(RuntimeError::build(format!(
+                "{}: expected a template string",
+                self.name()
+            )));
+        };
+        let arguments = iter.collect::<Vec<_>>();
+        let pieces = template.split("{}").collect::<Vec<_>>();
+        if pieces.len() - 1 != arguments.len() {
+            return Err(RuntimeError::build(format!(
+                "{}: expected {} arguments for the placeholders but got {}",
+                self.name(),
+                pieces.len() - 1,
+                arguments.len()
+            )));
+        }
+        let mut result = pieces[0].to_string();
+        for (argument, piece) in arguments.iter().zip(&pieces[1..]) {
+            result.push_str(&argument.display(env)?);
+            result.push_str(piece);
+        }
+        env.check_string_len(result.len())?;
+        Ok(Object::Str(result))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn max_arity(&self) -> usize {
+        usize::MAX
+    }
+
+    fn name(&self) -> &str {
+        "format"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the template string with each `{}` replaced by the next argument."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct TimeIt;
+
+impl Callable for TimeIt {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let function = match objects.into_iter().next() {
+            Some(Object::Callable(function)) if function.borrow().arity() == 0 => function,
+            _ => {
+                return Err(RuntimeError::build(format!(
+                    "{}: expected a function taking no arguments",
+                    self.name()
+                )))
+            }
+        };
+        let start = Instant::now();
+        let result = function.borrow().call(vec![], env)?;
+        let elapsed = Object::Number(start.elapsed().as_secs_f64());
+        Ok(Object::Array(Rc::new(RefCell::new(vec![result, elapsed]))))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "time_it"
+    }
+
+    fn doc(&self) -> &str {
+        "Calls a function without arguments and returns its result with the seconds it took."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct OrdBuiltin;
+
+impl Callable for OrdBuiltin {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        if let Some(Object::Str(s)) = objects.first() {
+            let mut chars = s.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                return Ok(Object::Number(c as u32 as f64));
+            }
+        }
+        Err(RuntimeError::build(format!(
+            "{}: expected a single-character string",
+            self.name()
+        )))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "ord"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the code point of a single-character string."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Chr;
+
+impl Callable for Chr {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        let Object::Number(n) = value else {
+            return Err(RuntimeError::build(format!(
+                "{}: expected a number",
+                self.name()
+            )));
+        };
+        if !value.is_integer() {
+            return Err(RuntimeError::build(format!(
+                "{}: expected an integer but got {n}",
+                self.name()
+            )));
+        }
+        match u32::try_from(*n as i64).ok().and_then(char::from_u32) {
+            Some(c) => Ok(Object::Str(c.to_string())),
+            None => Err(RuntimeError::build(format!(
+                "{}: {n} is not a valid code point",
+                self.name()
+            ))),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "chr"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the one-character string for the given code point."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+/// Calls `function` on behalf of the `builtin` callable, checking its arity first.
+fn call_back(
+    builtin: &str,
+    function: &CallableRef,
+    arguments: Vec<Object>,
+    env: &mut Environment,
+) -> LoxResult<Object> {
+    let function = function.borrow();
+    if arguments.len() < function.arity() || arguments.len() > function.max_arity() {
+        return Err(RuntimeError::build(format!(
+            "{builtin}: `{}` cannot be called with {} arguments",
+            function.name(),
+            arguments.len()
+        )));
+    }
+    function.call(arguments, env)
+}
+
+/// Splits the arguments of `map`, `filter` and `reduce` into the elements of
+/// the array and the callback.
+fn array_and_callback(
+    builtin: &str,
+    objects: Vec<Object>,
+) -> LoxResult<(Vec<Object>, CallableRef, Vec<Object>)> {
+    let mut iter = objects.into_iter();
+    match (iter.next(), iter.next()) {
+        (Some(Object::Array(array)), Some(Object::Callable(function))) => {
+            let elements = array.borrow().clone();
+            Ok((elements, function, iter.collect()))
+        }
+        _ => Err(RuntimeError::build(format!(
+            "{builtin}: expected an array and a function"
+        ))),
+    }
+}
+
+pub struct MapArray;
+
+impl Callable for MapArray {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let (elements, function, _) = array_and_callback(self.name(), objects)?;
+        let mapped = elements
+            .into_iter()
+            .map(|element| call_back(self.name(), &function, vec![element], env))
+            .collect::<LoxResult<Vec<_>>>()?;
+        Ok(Object::Array(Rc::new(RefCell::new(mapped))))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "map"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns a new array with the function applied to each element of the array."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct Filter;
+
+impl Callable for Filter {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let (elements, function, _) = array_and_callback(self.name(), objects)?;
+        let mut kept = vec![];
+        for element in elements {
+            if call_back(self.name(), &function, vec![element.clone()], env)?.into() {
+                kept.push(element);
+            }
+        }
+        Ok(Object::Array(Rc::new(RefCell::new(kept))))
+    }
+
+    fn arity(&self) -> usize {
+        2
+    }
+
+    fn name(&self) -> &str {
+        "filter"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns a new array with the elements of the array for which the predicate is truthy."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct Reduce;
+
+impl Callable for Reduce {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let (elements, function, rest) = array_and_callback(self.name(), objects)?;
+        let initial = rest.into_iter().next().expect("expected an initial value");
+        elements.into_iter().try_fold(initial, |acc, element| {
+            call_back(self.name(), &function, vec![acc, element], env)
+        })
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+
+    fn name(&self) -> &str {
+        "reduce"
+    }
+
+    fn doc(&self) -> &str {
+        "Combines the elements of the array from left to right with the function, starting from the initial value."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct Args;
+
+impl Callable for Args {
+    fn call(&self, _objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let args = env
+            .script_args()
+            .iter()
+            .map(|arg| Object::Str(arg.clone()))
+            .collect();
+        Ok(Object::Array(Rc::new(RefCell::new(args))))
+    }
+
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn name(&self) -> &str {
+        "args"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the command-line arguments given after the script."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct GetEnv;
+
+impl Callable for GetEnv {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let Some(Object::Str(name)) = objects.first() else {
+            return Err(RuntimeError::build(format!(
+                "{}: expected a variable name",
+                self.name()
+            )));
+        };
+        match env::var(name) {
+            Ok(value) => Ok(Object::Str(value)),
+            Err(env::VarError::NotPresent) => Ok(Object::Nil),
+            Err(env::VarError::NotUnicode(_)) => Err(RuntimeError::build(format!(
+                "{}: `{name}` is not valid unicode",
+                self.name()
+            ))),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "getenv"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the value of the given environment variable, or nil if it is not set."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+/// The integer value of `value`, or an error naming the `builtin` it was passed to.
+fn integer_argument(builtin: &str, value: &Object) -> LoxResult<i64> {
+    match value {
+        Object::Number(n) => value.as_integer().ok_or_else(|| {
+            RuntimeError::build(format!("{builtin}: expected an integer but got {n}"))
+        }),
+        _ => Err(RuntimeError::build(format!("{builtin}: expected a number"))),
+    }
+}
+
+pub struct Hex;
+
+impl Callable for Hex {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        let n = integer_argument(self.name(), value)?;
+        let sign = if n < 0 { "-" } else { "" };
+        Ok(Object::Str(format!("{sign}0x{:x}", n.unsigned_abs())))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "hex"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the hexadecimal representation of an integer, prefixed with `0x`."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Bin;
+
+impl Callable for Bin {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        let n = integer_argument(self.name(), value)?;
+        let sign = if n < 0 { "-" } else { "" };
+        Ok(Object::Str(format!("{sign}0b{:b}", n.unsigned_abs())))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "bin"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the binary representation of an integer, prefixed with `0b`."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Sci;
+
+impl Callable for Sci {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let Some(&Object::Number(n)) = objects.first() else {
+            return Err(RuntimeError::build(format!(
+                "{}: expected a number",
+                self.name()
+            )));
+        };
+        Ok(Object::Str(format!("{n:e}")))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "sci"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the scientific notation of a number, such as `1.2345e4`."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+/// The numbers passed to `builtin`, or an error if any argument is not a number.
+fn number_arguments(builtin: &str, objects: Vec<Object>) -> LoxResult<Vec<f64>> {
+    objects
+        .into_iter()
+        .map(|object| match object {
+            Object::Number(x) => Ok(x),
+            _ => Err(RuntimeError::build(format!("{builtin}: expected numbers"))),
+        })
+        .collect()
+}
+
+pub struct Abs;
+
+impl Callable for Abs {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let Some(&Object::Number(x)) = objects.first() else {
+            return Err(RuntimeError::build(format!(
+                "{}: expected a number",
+                self.name()
+            )));
+        };
+        // clears the sign of `-0` too
+        Ok(Object::Number(x.abs()))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "abs"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the absolute value of a number."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Min;
+
+impl Callable for Min {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let numbers = number_arguments(self.name(), objects)?;
+        // `-0` is smaller than `0`
+        let min = numbers.into_iter().reduce(|min, x| {
+            if x < min || (x == min && x.is_sign_negative()) {
+                x
+            } else {
+                min
+            }
+        });
+        Ok(Object::Number(min.expect("expected at least one argument")))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn max_arity(&self) -> usize {
+        usize::MAX
+    }
+
+    fn name(&self) -> &str {
+        "min"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the smallest of its arguments, which must be numbers."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Max;
+
+impl Callable for Max {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let numbers = number_arguments(self.name(), objects)?;
+        // `0` is greater than `-0`
+        let max = numbers.into_iter().reduce(|max, x| {
+            if x > max || (x == max && x.is_sign_positive()) {
+                x
+            } else {
+                max
+            }
+        });
+        Ok(Object::Number(max.expect("expected at least one argument")))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn max_arity(&self) -> usize {
+        usize::MAX
+    }
+
+    fn name(&self) -> &str {
+        "max"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns the greatest of its arguments, which must be numbers."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Clamp;
+
+impl Callable for Clamp {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let [x, lo, hi] = number_arguments(self.name(), objects)?[..] else {
+            unreachable!("expected three arguments");
+        };
+        if lo > hi {
+            return Err(RuntimeError::build(format!(
+                "{}: lower bound {lo} is greater than upper bound {hi}",
+                self.name()
+            )));
+        }
+        Ok(Object::Number(x.clamp(lo, hi)))
+    }
+
+    fn arity(&self) -> usize {
+        3
+    }
+
+    fn name(&self) -> &str {
+        "clamp"
+    }
+
+    fn doc(&self) -> &str {
+        "Restricts a number to the range between a lower and an upper bound."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Sign;
+
+impl Callable for Sign {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        let Some(&Object::Number(x)) = objects.first() else {
+            return Err(RuntimeError::build(format!(
+                "{}: expected a number",
+                self.name()
+            )));
+        };
+        let sign = if x > 0.0 {
+            1.0
+        } else if x < 0.0 {
+            -1.0
+        } else {
+            0.0
+        };
+        Ok(Object::Number(sign))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "sign"
+    }
+
+    fn doc(&self) -> &str {
+        "Returns -1, 0 or 1 depending on the sign of a number."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+}
+
+pub struct Print;
+
+impl Callable for Print {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        print!("{}", value.display(env)?);
+        io::stdout()
+            .flush()
+            .map_err(|e| RuntimeError::build(format!("{}: {e}", self.name())))?;
+        Ok(Object::Nil)
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "print"
+    }
+
+    fn doc(&self) -> &str {
+        "Prints its argument to the standard output, without a newline."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct Println;
+
+impl Callable for Println {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let value = objects.first().expect("expected one argument");
+        println!("{}", value.display(env)?);
+        Ok(Object::Nil)
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "println"
+    }
+
+    fn doc(&self) -> &str {
+        "Prints its argument to the standard output, followed by a newline."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+pub struct Dir;
+
+impl Callable for Dir {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let Some(object) = objects.first() else {
+            env.last().iter().for_each(|(name, _)| println!("{name}"));
+            return Ok(Object::Nil);
+        };
+        let fields = match object {
+            Object::Callable(callable) => callable.borrow().fields(),
+            _ => None,
+        };
+        let Some(fields) = fields else {
+            return Err(RuntimeError::build(format!(
+                "{}: expected an instance but got {}",
+                self.name(),
+                object.type_name()
+            )));
+        };
+        let mut names = fields.keys().collect::<Vec<_>>();
+        names.sort();
+        names.iter().for_each(|name| println!("{name}"));
+        Ok(Object::Nil)
+    }
+
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn max_arity(&self) -> usize {
+        1
+    }
+
+    fn name(&self) -> &str {
+        "dir"
+    }
+
+    fn doc(&self) -> &str {
+        "Prints all the names in the current scope, or the fields of the given instance."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+/// Returns the built-in called when `name` is used as a method on a string.
+pub fn string_method(name: &str) -> Option<Box<dyn Callable>> {
+    let method: Box<dyn Callable> = match name {
+        "upper" => Box::new(Upper),
+        "lower" => Box::new(Lower),
+        "trim" => Box::new(Trim),
+        "len" => Box::new(Len),
+        "split" => Box::new(Split),
+        "contains" => Box::new(Contains),
+        "index_of" => Box::new(IndexOf),
+        _ => return None,
+    };
+    Some(method)
+}
+
+/// A built-in whose first argument is bound to a receiver, e.g. `"abc".upper`.
+pub struct BoundMethod {
+    receiver: Object,
+    method: Box<dyn Callable>,
+}
+
+impl BoundMethod {
+    pub fn new(receiver: Object, method: Box<dyn Callable>) -> Self {
+        Self { receiver, method }
+    }
+}
+
+impl Callable for BoundMethod {
+    fn call(&self, objects: Vec<Object>, env: &mut Environment) -> LoxResult<Object> {
+        let mut arguments = vec![self.receiver.clone()];
+        arguments.extend(objects);
+        self.method.call(arguments, env)
+    }
+
+    fn arity(&self) -> usize {
+        self.method.arity() - 1
+    }
+
+    fn max_arity(&self) -> usize {
+        self.method.max_arity() - 1
+    }
+
+    fn name(&self) -> &str {
+        self.method.name()
+    }
+
+    fn doc(&self) -> &str {
+        self.method.doc()
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+
+    fn is_pure(&self) -> bool {
+        self.method.is_pure()
+    }
+}
+
+#[derive(Clone)]
+pub struct UserDefinedFunction {
+    name: Symbol,
+    body: Rc<[Stmt]>,
+    parameters: Rc<[Symbol]>,
+    defaults: Rc<[Expression]>,
+    /// Shared by the copies of the function, each call running in its own clone.
+    closure: Rc<Environment>,
+}
+
+impl UserDefinedFunction {
+    pub fn new(
+        name: Symbol,
+        body: Rc<[Stmt]>,
+        parameters: Rc<[Symbol]>,
+        defaults: Rc<[Expression]>,
+        closure: Environment,
+    ) -> Self {
+        Self {
+            name,
+            body,
+            parameters,
+            defaults,
+            closure: Rc::new(closure),
+        }
+    }
+
+    fn bind(&self, obj: &Instance) -> LoxResult<Self> {
+        let mut method = self.clone();
+        let closure = Rc::make_mut(&mut method.closure);
+        closure.enter_block();
+        for (name, object) in &obj.fields {
+            closure.define(Symbol::intern(name), Some(object.clone()))?;
+        }
+        closure.define(
+            // this should be a reference to the object, not a copy of it
+            Symbol::intern("this"),
+            Some(Object::Callable(Rc::new(RefCell::new(obj.clone())))),
+        )?;
+        Ok(method)
+    }
+
+    /// Runs the body with the name of the function bound to `function`. Unless
+    /// that is a wrapper, which must see every call, a tail call to `function`
+    /// runs in a new frame of this loop instead of growing the stack.
+    #[inline(always)]
+    fn run(
+        &self,
+        objects: Vec<Object>,
+        function: CallableRef,
+        tail_loop: bool,
+    ) -> LoxResult<Object> {
+        let mut objects = objects;
+        let interpreter = self.closure.interpreter();
+        loop {
+            let mut env = Environment::clone(&self.closure);
+            env.enter_block();
+            env.define(self.name, Some(Object::Callable(Rc::clone(&function))))?;
+            let given = objects.len();
+            for (param, value) in self.parameters.iter().zip(objects) {
+                env.define(*param, Some(value))?;
+            }
+            // missing arguments are the trailing ones, which all have a default value
+            let missing = self.parameters.len() - given;
+            let defaults = &self.defaults[self.defaults.len() - missing..];
+            for (param, default) in self.parameters[given..].iter().zip(defaults) {
+                let value = default.evaluate(&mut env)?;
+                env.define(*param, Some(value))?;
+            }
+            match interpreter.interpret(&mut env, &self.body)? {
+                Some(Signal::TailCall {
+                    callee, arguments, ..
+                }) if tail_loop && Rc::ptr_eq(&callee, &function) => objects = arguments,
+                Some(Signal::TailCall {
+                    callee,
+                    arguments,
+                    line,
+                }) => {
+                    let result = callee.borrow().call(arguments, &mut env);
+                    return result.map_err(|e| e.at_line(line));
+                }
+                Some(Signal::Return(value)) => return Ok(value),
+                _ => return Ok(Object::Nil),
+            }
+        }
+    }
+}
+
+impl Callable for UserDefinedFunction {
+    fn call(&self, objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        self.run(objects, Rc::new(RefCell::new(self.clone())), true)
+    }
+
+    fn call_as(
+        &self,
+        objects: Vec<Object>,
+        _env: &mut Environment,
+        itself: CallableRef,
+    ) -> LoxResult<Object> {
+        self.run(objects, itself, false)
+    }
+
+    fn arity(&self) -> usize {
+        self.parameters.len() - self.defaults.len()
+    }
+
+    fn max_arity(&self) -> usize {
+        self.parameters.len()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Function
+    }
+}
+
+#[derive(Clone)]
+pub struct UserDefinedStruct {
+    id: usize,
+    name: String,
+    methods: HashMap<String, UserDefinedFunction>,
+    /// Methods called on the class itself, without an instance to bind `this` to.
+    static_methods: HashMap<String, UserDefinedFunction>,
+    /// Methods called without arguments when reading the property of their name.
+    getters: HashMap<String, UserDefinedFunction>,
+}
+
+impl UserDefinedStruct {
+    pub fn new(
+        name: String,
+        methods: HashMap<String, UserDefinedFunction>,
+        static_methods: HashMap<String, UserDefinedFunction>,
+        getters: HashMap<String, UserDefinedFunction>,
+    ) -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            name,
+            methods,
+            static_methods,
+            getters,
+        }
+    }
+
+    fn find_method(&self, name: &str) -> Option<UserDefinedFunction> {
+        self.methods.get(name).cloned()
+    }
+}
+
+impl Callable for UserDefinedStruct {
+    fn call(&self, _objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        Ok(Object::Callable(Rc::new(RefCell::new(Instance::new(
+            self.clone(),
+        )))))
+    }
+
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn doc(&self) -> &str {
+        "No documentation available."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Class
+    }
+
+    fn class_id(&self) -> Option<usize> {
+        Some(self.id)
+    }
+
+    fn get(&self, name: &str) -> LoxResult<Object> {
+        match self.static_methods.get(name) {
+            Some(method) => Ok(Object::Callable(Rc::new(RefCell::new(method.clone())))),
+            None => Err(RuntimeError::build(format!(
+                "undefined static method `{}` on class `{}`",
+                name, self.name
+            ))),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Instance {
+    base: UserDefinedStruct,
+    fields: HashMap<String, Object>,
+}
+
+impl Instance {
+    pub fn new(base: UserDefinedStruct) -> Instance {
+        Self {
+            base,
+            fields: HashMap::new(),
+        }
+    }
+}
+
+impl Callable for Instance {
+    fn call(&self, _objects: Vec<Object>, _env: &mut Environment) -> LoxResult<Object> {
+        todo!();
+    }
+
+    fn arity(&self) -> usize {
+        0
+    }
+    fn name(&self) -> &str {
+        self.base.name()
+    }
+
+    fn doc(&self) -> &str {
+        "A class instance."
+    }
+
+    fn r#type(&self) -> CallableType {
+        CallableType::Instance
+    }
+
+    fn get(&self, name: &str) -> LoxResult<Object> {
+        if let Some(obj) = self.fields.get(name) {
+            Ok(obj.clone())
+        } else if let Some(method) = self.base.find_method(name) {
+            let method = method.bind(self)?;
+            Ok(Object::Callable(Rc::new(RefCell::new(method))))
+        } else {
+            Err(RuntimeError::build(format!(
+                "undefined property `{}`",
+                name
+            )))
+        }
+    }
+
+    fn set(&mut self, name: &str, value: Object) -> LoxResult<()> {
+        self.fields.insert(name.to_owned(), value);
+        Ok(())
+    }
+
+    fn fields(&self) -> Option<HashMap<String, Object>> {
+        Some(self.fields.clone())
+    }
+
+    fn getter(&self, name: &str) -> LoxResult<Option<CallableRef>> {
+        if self.fields.contains_key(name) {
+            return Ok(None);
+        }
+        match self.base.getters.get(name) {
+            Some(getter) => Ok(Some(Rc::new(RefCell::new(getter.bind(self)?)))),
+            None => Ok(None),
+        }
+    }
+
+    fn has_property(&self, name: &str) -> bool {
+        self.fields.contains_key(name)
+            || self.base.methods.contains_key(name)
+            || self.base.getters.contains_key(name)
+    }
+
+    fn class_id(&self) -> Option<usize> {
+        self.base.class_id()
     }
 }
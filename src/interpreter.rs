@@ -1,18 +1,404 @@
 use crate::errors::{LoxResult, RuntimeError};
-use crate::expression::{Expression, Object};
+use crate::expression::{prepare_call, Expression, Object};
 use crate::functions::{
-    Clock, Dir, Exit, Help, Print, Quit, Rand, Randint, Round, Type, UserDefinedFunction,
-    UserDefinedStruct,
+    Abs, Args, Assert, AssertEq, Between, Bin, CallableRef, Chr, Clamp, Clock, Contains, DeepEq,
+    Dir, Exit, Filter, Format, GetEnv, GetOr, GroupDigits, Help, Hex, IndexOf, IsInteger, Join,
+    Keys, Len, LimitCallsBuiltin, Lower, MapArray, Max, Min, NumCmp, OrdBuiltin, Print, Println,
+    Quit, Rand, Randint, Range, ReadBytes, ReadFile, Reduce, Repr, Round, Sci, Sign, Sleep, Split,
+    TimeIt, Trim, Type, TypeOf, Upper, UserDefinedFunction, UserDefinedStruct, Values, WriteBytes,
+    WriteFile,
 };
 use crate::parser::Stmt;
+use crate::symbol::Symbol;
 use std::cell::RefCell;
-use std::collections::hash_map::Entry::Occupied;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::rc::Rc;
 
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Maximum length in bytes of the strings built by the program, at most
+    /// `MAX_STRING_LEN` when unset.
+    pub max_string_len: Option<usize>,
+    /// Forbid redefining or reassigning the built-in names in the global scope.
+    pub protect_builtins: bool,
+    /// Report the global names added, changed or removed by each REPL line.
+    pub show_changes: bool,
+    /// Print each statement and call to the standard error before running it.
+    pub trace: bool,
+    /// The command-line arguments following the script, returned by `args()`.
+    pub args: Rc<[String]>,
+}
+
+/// Maximum length in bytes of the strings built by a program without a limit of
+/// its own, which still fit in memory.
+pub const MAX_STRING_LEN: usize = 1 << 30;
+
+/// The representation of every global binding at some point in time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary(BTreeMap<String, String>);
+
+impl Summary {
+    /// Lists the differences between this summary and a later one.
+    pub fn changes(&self, after: &Summary) -> Vec<Change> {
+        let mut changes = vec![];
+        for (name, value) in &after.0 {
+            match self.0.get(name) {
+                None => changes.push(Change::Added(name.clone())),
+                Some(before) if before != value => changes.push(Change::Changed(name.clone())),
+                _ => (),
+            }
+        }
+        for name in self.0.keys() {
+            if !after.0.contains_key(name) {
+                changes.push(Change::Removed(name.clone()));
+            }
+        }
+        changes
+    }
+}
+
+/// The bindings of the global scope, taken by `Environment::snapshot`.
 #[derive(Clone, Debug)]
-pub struct Environment(Vec<HashMap<String, Option<Object>>>);
+pub struct EnvSnapshot(Scope);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added(String),
+    Changed(String),
+    Removed(String),
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added(name) => write!(f, "added: {name}"),
+            Change::Changed(name) => write!(f, "changed: {name}"),
+            Change::Removed(name) => write!(f, "removed: {name}"),
+        }
+    }
+}
+
+/// The bindings of a block, in the order they were defined. A name is looked up
+/// by scanning the symbols of the block, which hold few names, and its value is
+/// then read from the slot at the same index, without hashing. Past
+/// `LINEAR_SCAN_LIMIT` names, as in the global scope, the slots are indexed by
+/// name instead.
+#[derive(Clone, Debug, Default)]
+pub struct Scope {
+    names: Vec<Symbol>,
+    slots: Vec<Option<Object>>,
+    /// Whether each slot was bound with `const`.
+    constants: Vec<bool>,
+    /// The slot of each name, only filled once the scope holds more than
+    /// `LINEAR_SCAN_LIMIT` names.
+    index: HashMap<Symbol, usize>,
+}
+
+const LINEAR_SCAN_LIMIT: usize = 16;
+
+impl Scope {
+    fn slot(&self, name: Symbol) -> Option<usize> {
+        if self.index.is_empty() {
+            self.names.iter().position(|&n| n == name)
+        } else {
+            self.index.get(&name).copied()
+        }
+    }
+
+    /// Binds `name` to a new slot, or overwrites the slot it already has.
+    pub fn insert(&mut self, name: Symbol, value: Option<Object>) {
+        self.bind(name, value, false);
+    }
+
+    fn bind(&mut self, name: Symbol, value: Option<Object>, constant: bool) {
+        match self.slot(name) {
+            Some(slot) => {
+                self.slots[slot] = value;
+                self.constants[slot] = constant;
+            }
+            None => {
+                self.names.push(name);
+                self.slots.push(value);
+                self.constants.push(constant);
+                if !self.index.is_empty() {
+                    self.index.insert(name, self.names.len() - 1);
+                } else if self.names.len() > LINEAR_SCAN_LIMIT {
+                    self.index = self
+                        .names
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &n)| (n, i))
+                        .collect();
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, name: Symbol) -> Option<&Option<Object>> {
+        self.slot(name).map(|slot| &self.slots[slot])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &Option<Object>)> {
+        self.names.iter().copied().zip(&self.slots)
+    }
+
+    fn clear(&mut self) {
+        self.names.clear();
+        self.slots.clear();
+        self.constants.clear();
+        self.index.clear();
+    }
+}
+
+/// The global scope of a new environment, holding every built-in.
+fn prelude() -> Scope {
+    let mut map = Scope::default();
+    map.insert(
+        Symbol::intern("assert"),
+        Some(Object::Callable(Rc::new(RefCell::new(Assert)))),
+    );
+    map.insert(
+        Symbol::intern("clock"),
+        Some(Object::Callable(Rc::new(RefCell::new(Clock)))),
+    );
+    map.insert(
+        Symbol::intern("print"),
+        Some(Object::Callable(Rc::new(RefCell::new(Print)))),
+    );
+    map.insert(
+        Symbol::intern("println"),
+        Some(Object::Callable(Rc::new(RefCell::new(Println)))),
+    );
+    map.insert(
+        Symbol::intern("help"),
+        Some(Object::Callable(Rc::new(RefCell::new(Help)))),
+    );
+    map.insert(
+        Symbol::intern("exit"),
+        Some(Object::Callable(Rc::new(RefCell::new(Exit)))),
+    );
+    map.insert(
+        Symbol::intern("quit"),
+        Some(Object::Callable(Rc::new(RefCell::new(Quit)))),
+    );
+    map.insert(
+        Symbol::intern("type"),
+        Some(Object::Callable(Rc::new(RefCell::new(Type)))),
+    );
+    map.insert(
+        Symbol::intern("dir"),
+        Some(Object::Callable(Rc::new(RefCell::new(Dir)))),
+    );
+    map.insert(
+        Symbol::intern("rand"),
+        Some(Object::Callable(Rc::new(RefCell::new(Rand)))),
+    );
+    map.insert(
+        Symbol::intern("randint"),
+        Some(Object::Callable(Rc::new(RefCell::new(Randint)))),
+    );
+    map.insert(
+        Symbol::intern("round"),
+        Some(Object::Callable(Rc::new(RefCell::new(Round)))),
+    );
+    map.insert(
+        Symbol::intern("group_digits"),
+        Some(Object::Callable(Rc::new(RefCell::new(GroupDigits)))),
+    );
+    map.insert(
+        Symbol::intern("repr"),
+        Some(Object::Callable(Rc::new(RefCell::new(Repr)))),
+    );
+    map.insert(
+        Symbol::intern("len"),
+        Some(Object::Callable(Rc::new(RefCell::new(Len)))),
+    );
+    map.insert(
+        Symbol::intern("read_bytes"),
+        Some(Object::Callable(Rc::new(RefCell::new(ReadBytes)))),
+    );
+    map.insert(
+        Symbol::intern("write_bytes"),
+        Some(Object::Callable(Rc::new(RefCell::new(WriteBytes)))),
+    );
+    map.insert(
+        Symbol::intern("upper"),
+        Some(Object::Callable(Rc::new(RefCell::new(Upper)))),
+    );
+    map.insert(
+        Symbol::intern("lower"),
+        Some(Object::Callable(Rc::new(RefCell::new(Lower)))),
+    );
+    map.insert(
+        Symbol::intern("trim"),
+        Some(Object::Callable(Rc::new(RefCell::new(Trim)))),
+    );
+    map.insert(
+        Symbol::intern("is_integer"),
+        Some(Object::Callable(Rc::new(RefCell::new(IsInteger)))),
+    );
+    map.insert(
+        Symbol::intern("split"),
+        Some(Object::Callable(Rc::new(RefCell::new(Split)))),
+    );
+    map.insert(
+        Symbol::intern("join"),
+        Some(Object::Callable(Rc::new(RefCell::new(Join)))),
+    );
+    map.insert(
+        Symbol::intern("range"),
+        Some(Object::Callable(Rc::new(RefCell::new(Range)))),
+    );
+    map.insert(
+        Symbol::intern("keys"),
+        Some(Object::Callable(Rc::new(RefCell::new(Keys)))),
+    );
+    map.insert(
+        Symbol::intern("values"),
+        Some(Object::Callable(Rc::new(RefCell::new(Values)))),
+    );
+    map.insert(
+        Symbol::intern("contains"),
+        Some(Object::Callable(Rc::new(RefCell::new(Contains)))),
+    );
+    map.insert(
+        Symbol::intern("index_of"),
+        Some(Object::Callable(Rc::new(RefCell::new(IndexOf)))),
+    );
+    map.insert(
+        Symbol::intern("read_file"),
+        Some(Object::Callable(Rc::new(RefCell::new(ReadFile)))),
+    );
+    map.insert(
+        Symbol::intern("write_file"),
+        Some(Object::Callable(Rc::new(RefCell::new(WriteFile)))),
+    );
+    map.insert(
+        Symbol::intern("limit_calls"),
+        Some(Object::Callable(Rc::new(RefCell::new(LimitCallsBuiltin)))),
+    );
+    map.insert(
+        Symbol::intern("between"),
+        Some(Object::Callable(Rc::new(RefCell::new(Between)))),
+    );
+    map.insert(
+        Symbol::intern("get_or"),
+        Some(Object::Callable(Rc::new(RefCell::new(GetOr)))),
+    );
+    map.insert(
+        Symbol::intern("typeof"),
+        Some(Object::Callable(Rc::new(RefCell::new(TypeOf)))),
+    );
+    map.insert(
+        Symbol::intern("deep_eq"),
+        Some(Object::Callable(Rc::new(RefCell::new(DeepEq)))),
+    );
+    map.insert(
+        Symbol::intern("sleep"),
+        Some(Object::Callable(Rc::new(RefCell::new(Sleep)))),
+    );
+    map.insert(
+        Symbol::intern("num_cmp"),
+        Some(Object::Callable(Rc::new(RefCell::new(NumCmp)))),
+    );
+    map.insert(
+        Symbol::intern("format"),
+        Some(Object::Callable(Rc::new(RefCell::new(Format)))),
+    );
+    map.insert(
+        Symbol::intern("time_it"),
+        Some(Object::Callable(Rc::new(RefCell::new(TimeIt)))),
+    );
+    map.insert(
+        Symbol::intern("ord"),
+        Some(Object::Callable(Rc::new(RefCell::new(OrdBuiltin)))),
+    );
+    map.insert(
+        Symbol::intern("chr"),
+        Some(Object::Callable(Rc::new(RefCell::new(Chr)))),
+    );
+    map.insert(
+        Symbol::intern("map"),
+        Some(Object::Callable(Rc::new(RefCell::new(MapArray)))),
+    );
+    map.insert(
+        Symbol::intern("filter"),
+        Some(Object::Callable(Rc::new(RefCell::new(Filter)))),
+    );
+    map.insert(
+        Symbol::intern("reduce"),
+        Some(Object::Callable(Rc::new(RefCell::new(Reduce)))),
+    );
+    map.insert(
+        Symbol::intern("args"),
+        Some(Object::Callable(Rc::new(RefCell::new(Args)))),
+    );
+    map.insert(
+        Symbol::intern("getenv"),
+        Some(Object::Callable(Rc::new(RefCell::new(GetEnv)))),
+    );
+    map.insert(
+        Symbol::intern("hex"),
+        Some(Object::Callable(Rc::new(RefCell::new(Hex)))),
+    );
+    map.insert(
+        Symbol::intern("bin"),
+        Some(Object::Callable(Rc::new(RefCell::new(Bin)))),
+    );
+    map.insert(
+        Symbol::intern("sci"),
+        Some(Object::Callable(Rc::new(RefCell::new(Sci)))),
+    );
+    map.insert(
+        Symbol::intern("abs"),
+        Some(Object::Callable(Rc::new(RefCell::new(Abs)))),
+    );
+    map.insert(
+        Symbol::intern("min"),
+        Some(Object::Callable(Rc::new(RefCell::new(Min)))),
+    );
+    map.insert(
+        Symbol::intern("max"),
+        Some(Object::Callable(Rc::new(RefCell::new(Max)))),
+    );
+    map.insert(
+        Symbol::intern("clamp"),
+        Some(Object::Callable(Rc::new(RefCell::new(Clamp)))),
+    );
+    map.insert(
+        Symbol::intern("sign"),
+        Some(Object::Callable(Rc::new(RefCell::new(Sign)))),
+    );
+    map.insert(
+        Symbol::intern("assert_eq"),
+        Some(Object::Callable(Rc::new(RefCell::new(AssertEq)))),
+    );
+    map
+}
+
+/// Cleared scopes kept by `exit_block` for `enter_block` to reuse, so that blocks
+/// executed in a loop do not allocate new slots on each iteration.
+#[derive(Debug, Default)]
+struct ScopePool(Vec<Scope>);
+
+/// A cloned environment (a closure) starts with an empty pool: the slots are only
+/// worth keeping around for the environment that freed them.
+impl Clone for ScopePool {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Environment {
+    scopes: Vec<Scope>,
+    pool: ScopePool,
+    builtins: Rc<HashSet<Symbol>>,
+    /// Shared by every closure, so that function calls run in the same interpreter.
+    interpreter: Rc<Interpreter>,
+    options: Options,
+}
 
 impl Default for Environment {
     fn default() -> Self {
@@ -22,61 +408,76 @@ impl Default for Environment {
 
 impl Environment {
     pub fn new() -> Self {
-        let mut map = HashMap::new();
-        map.insert(
-            "clock".to_string(),
-            Some(Object::Callable(Rc::new(RefCell::new(Clock)))),
-        );
-        map.insert(
-            "print".to_string(),
-            Some(Object::Callable(Rc::new(RefCell::new(Print)))),
-        );
-        map.insert(
-            "help".to_string(),
-            Some(Object::Callable(Rc::new(RefCell::new(Help)))),
-        );
-        map.insert(
-            "exit".to_string(),
-            Some(Object::Callable(Rc::new(RefCell::new(Exit)))),
-        );
-        map.insert(
-            "quit".to_string(),
-            Some(Object::Callable(Rc::new(RefCell::new(Quit)))),
-        );
-        map.insert(
-            "type".to_string(),
-            Some(Object::Callable(Rc::new(RefCell::new(Type)))),
-        );
-        map.insert(
-            "dir".to_string(),
-            Some(Object::Callable(Rc::new(RefCell::new(Dir)))),
-        );
-        map.insert(
-            "rand".to_string(),
-            Some(Object::Callable(Rc::new(RefCell::new(Rand)))),
-        );
-        map.insert(
-            "randint".to_string(),
-            Some(Object::Callable(Rc::new(RefCell::new(Randint)))),
-        );
-        map.insert(
-            "round".to_string(),
-            Some(Object::Callable(Rc::new(RefCell::new(Round)))),
-        );
-        Self(vec![map])
-    }
-
-    pub fn define(&mut self, name: &str, value: Option<Object>) {
-        self.0
-            .last_mut()
-            .expect("no environment were found")
-            .insert(name.to_string(), value);
+        Self::with_options(Options::default())
+    }
+
+    pub fn with_options(options: Options) -> Self {
+        Self::with_globals(prelude(), options)
+    }
+
+    /// An environment without any built-in, so that a script cannot call `exit`
+    /// or read files unless the host adds them with `add_builtin`.
+    pub fn bare() -> Self {
+        Self::bare_with_options(Options::default())
+    }
+
+    pub fn bare_with_options(options: Options) -> Self {
+        Self::with_globals(Scope::default(), options)
+    }
+
+    fn with_globals(globals: Scope, options: Options) -> Self {
+        Self {
+            builtins: Rc::new(globals.names.iter().copied().collect()),
+            scopes: vec![globals],
+            pool: ScopePool::default(),
+            interpreter: Rc::new(Interpreter::with_trace(options.trace)),
+            options,
+        }
+    }
+
+    /// Defines the built-in `name` in the global scope.
+    pub fn add_builtin(&mut self, name: &str) -> LoxResult<()> {
+        let name = Symbol::intern(name);
+        let Some(value) = prelude().get(name).cloned() else {
+            return Err(RuntimeError::build(format!("no built-in named `{name}`")));
+        };
+        self.scopes[0].insert(name, value);
+        Rc::make_mut(&mut self.builtins).insert(name);
+        Ok(())
+    }
+
+    pub fn define(&mut self, name: Symbol, value: Option<Object>) -> LoxResult<()> {
+        self.bind(name, value, false)
     }
 
-    pub fn update(&mut self, name: &str, value: Object) -> LoxResult<()> {
-        for env in self.0.iter_mut().rev() {
-            if let Occupied(ref mut entry) = env.entry(name.to_string()) {
-                *entry.get_mut() = Some(value.clone());
+    /// Defines a binding that cannot be reassigned.
+    pub fn define_const(&mut self, name: Symbol, value: Object) -> LoxResult<()> {
+        self.bind(name, Some(value), true)
+    }
+
+    fn bind(&mut self, name: Symbol, value: Option<Object>, constant: bool) -> LoxResult<()> {
+        self.check_builtin(self.scopes.len() - 1, name)?;
+        let scope = self.last_mut();
+        if scope.slot(name).is_some_and(|slot| scope.constants[slot]) {
+            return Err(RuntimeError::build(format!(
+                "cannot redefine const `{name}`"
+            )));
+        }
+        scope.bind(name, value, constant);
+        Ok(())
+    }
+
+    pub fn update(&mut self, name: Symbol, value: Object) -> LoxResult<()> {
+        for depth in (0..self.scopes.len()).rev() {
+            if let Some(slot) = self.scopes[depth].slot(name) {
+                self.check_builtin(depth, name)?;
+                let scope = &mut self.scopes[depth];
+                if scope.constants[slot] {
+                    return Err(RuntimeError::build(format!(
+                        "cannot assign to const `{name}`"
+                    )));
+                }
+                scope.slots[slot] = Some(value);
                 return Ok(());
             }
         }
@@ -84,9 +485,9 @@ impl Environment {
         Err(RuntimeError::build(format!("name `{name}` is not defined")))
     }
 
-    pub fn get(&self, name: &str) -> LoxResult<&Option<Object>> {
-        for env in self.0.iter().rev() {
-            if let Some(obj) = env.get(name) {
+    pub fn get(&self, name: Symbol) -> LoxResult<&Option<Object>> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(obj) = scope.get(name) {
                 return Ok(obj);
             }
         }
@@ -94,44 +495,199 @@ impl Environment {
         Err(RuntimeError::build(format!("name `{name}` is not defined")))
     }
 
-    pub fn last(&self) -> &HashMap<String, Option<Object>> {
-        self.0
+    pub fn last(&self) -> &Scope {
+        self.scopes
             .last()
             .expect("should at least contain the global scope")
     }
 
-    pub fn last_mut(&mut self) -> &mut HashMap<String, Option<Object>> {
-        self.0
+    pub fn last_mut(&mut self) -> &mut Scope {
+        self.scopes
             .last_mut()
             .expect("should at least contain the global scope")
     }
 
+    fn check_builtin(&self, depth: usize, name: Symbol) -> LoxResult<()> {
+        if self.options.protect_builtins && depth == 0 && self.builtins.contains(&name) {
+            Err(RuntimeError::build(format!(
+                "cannot overwrite built-in `{name}`"
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn interpreter(&self) -> Rc<Interpreter> {
+        Rc::clone(&self.interpreter)
+    }
+
+    pub fn trace(&self) -> bool {
+        self.options.trace
+    }
+
+    pub fn script_args(&self) -> &[String] {
+        &self.options.args
+    }
+
+    pub fn check_string_len(&self, len: usize) -> LoxResult<()> {
+        let max = self.options.max_string_len.unwrap_or(MAX_STRING_LEN);
+        if len > max {
+            Err(RuntimeError::build(format!(
+                "string of length {len} exceeds the maximum length of {max}"
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn summary(&self) -> Summary {
+        let globals = self
+            .scopes
+            .first()
+            .expect("should at least contain the global scope");
+        Summary(
+            globals
+                .iter()
+                .map(|(name, value)| {
+                    let repr = match value {
+                        Some(obj) => format!("{obj:?}"),
+                        None => "nil".to_string(),
+                    };
+                    (name.to_string(), repr)
+                })
+                .collect(),
+        )
+    }
+
+    /// Captures the bindings of the global scope, for `restore` to revert to.
+    pub fn snapshot(&self) -> EnvSnapshot {
+        EnvSnapshot(
+            self.scopes
+                .first()
+                .expect("should at least contain the global scope")
+                .clone(),
+        )
+    }
+
+    /// Reverts the global scope to the bindings it had when `snapshot` was taken:
+    /// the names defined since are removed and the others are bound to their old
+    /// values. Arrays and instances are shared, so changes made to their contents
+    /// are kept.
+    pub fn restore(&mut self, snapshot: EnvSnapshot) {
+        self.scopes[0] = snapshot.0;
+    }
+
     pub fn enter_block(&mut self) {
-        self.0.push(HashMap::new());
+        let scope = self.pool.0.pop().unwrap_or_default();
+        self.scopes.push(scope);
     }
 
     pub fn exit_block(&mut self) {
-        self.0.pop();
+        if let Some(mut scope) = self.scopes.pop() {
+            scope.clear();
+            self.pool.0.push(scope);
+        }
     }
 }
 
+/// The methods of a class by name, closing over the environment of its declaration.
+fn class_methods(methods: &[Stmt], env: &Environment) -> HashMap<String, UserDefinedFunction> {
+    let mut class_methods = HashMap::new();
+    for method in methods {
+        if let Stmt::Function {
+            name,
+            body,
+            parameters,
+            defaults,
+        } = method
+        {
+            class_methods.insert(
+                name.to_string(),
+                UserDefinedFunction::new(
+                    *name,
+                    Rc::clone(body),
+                    Rc::clone(parameters),
+                    Rc::clone(defaults),
+                    env.clone(),
+                ),
+            );
+        } else {
+            panic!("expected a function");
+        }
+    }
+    class_methods
+}
+
 pub enum Signal {
-    Continue,
-    Break,
-    Return(Option<Expression>),
+    Continue(Option<String>),
+    Break(Option<String>),
+    Return(Object),
+    /// A `return` of a call, left to the function returning so that calling
+    /// itself does not grow the Rust stack.
+    TailCall {
+        callee: CallableRef,
+        arguments: Vec<Object>,
+        line: usize,
+    },
 }
 
 impl Display for Signal {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Signal::Continue => write!(f, "continue"),
-            Signal::Break => write!(f, "break"),
+            Signal::Continue(None) => write!(f, "continue"),
+            Signal::Continue(Some(label)) => write!(f, "continue {label}"),
+            Signal::Break(None) => write!(f, "break"),
+            Signal::Break(Some(label)) => write!(f, "break {label}"),
             Signal::Return(obj) => write!(f, "return ({obj:?})"),
+            Signal::TailCall {
+                callee, arguments, ..
+            } => write!(f, "return {}({arguments:?})", callee.borrow().name()),
         }
     }
 }
 
-pub struct Interpreter;
+fn define_function(
+    name: Symbol,
+    body: &Rc<[Stmt]>,
+    parameters: &Rc<[Symbol]>,
+    defaults: &Rc<[Expression]>,
+    env: &mut Environment,
+) -> LoxResult<()> {
+    let func = UserDefinedFunction::new(
+        name,
+        Rc::clone(body),
+        Rc::clone(parameters),
+        Rc::clone(defaults),
+        env.clone(),
+    );
+    env.define(name, Some(Object::Callable(Rc::new(RefCell::new(func)))))
+}
+
+fn define_class(
+    name: Symbol,
+    methods: &[Stmt],
+    static_methods: &[Stmt],
+    getters: &[Stmt],
+    env: &mut Environment,
+) -> LoxResult<()> {
+    let class = UserDefinedStruct::new(
+        name.to_string(),
+        class_methods(methods, env),
+        class_methods(static_methods, env),
+        class_methods(getters, env),
+    );
+    env.define(name, Some(Object::Callable(Rc::new(RefCell::new(class)))))
+}
+
+/// Whether a `break` or `continue` with the given target applies to a loop.
+fn targets(target: &Option<String>, label: &Option<String>) -> bool {
+    target.is_none() || target == label
+}
+
+#[derive(Debug)]
+pub struct Interpreter {
+    trace: bool,
+}
 
 impl Default for Interpreter {
     fn default() -> Self {
@@ -141,129 +697,269 @@ impl Default for Interpreter {
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self
+        Self::with_trace(false)
+    }
+
+    /// An interpreter printing each statement to the standard error before executing it.
+    pub fn with_trace(trace: bool) -> Self {
+        Self { trace }
+    }
+
+    fn trace(&self, statement: &Stmt) {
+        if self.trace {
+            let tree = statement.tree();
+            eprintln!("[trace] {}", tree.lines().next().unwrap_or_default());
+        }
     }
 
-    #[allow(clippy::only_used_in_recursion)]
     pub fn execute(&self, statement: &Stmt, env: &mut Environment) -> LoxResult<Option<Signal>> {
+        self.trace(statement);
+        // statements are run by separate methods, so that the frame of this one,
+        // which grows with each level of nesting, stays small
         match statement {
             Stmt::Var { name, initializer } => {
-                let eval = initializer
-                    .as_ref()
-                    .map(|expr| expr.evaluate(env))
-                    .transpose()?;
-                env.define(name, eval);
+                let value = match initializer {
+                    Some(initializer) => Some(initializer.evaluate(env)?),
+                    None => None,
+                };
+                env.define(*name, value)?;
+            }
+            Stmt::Const { name, initializer } => {
+                let value = initializer.evaluate(env)?;
+                env.define_const(*name, value)?;
             }
             Stmt::Function {
                 name,
                 body,
                 parameters,
-            } => {
-                let func = UserDefinedFunction::new(
-                    name.clone(),
-                    body.clone(),
-                    parameters.clone(),
-                    env.clone(),
-                );
-                env.define(name, Some(Object::Callable(Rc::new(RefCell::new(func)))));
-            }
-            Stmt::Block(block) => {
-                env.enter_block();
-                for s in block {
-                    let control = self.execute(s, env)?;
-                    if control.is_some() {
-                        env.exit_block();
-                        return Ok(control);
-                    }
-                }
-                env.exit_block();
-            }
+                defaults,
+            } => define_function(*name, body, parameters, defaults, env)?,
+            Stmt::Block(block) => return self.block(block, env),
             Stmt::Expr(expression) => {
                 expression.evaluate(env)?;
             }
             Stmt::If {
-                condition,
-                then_stmt,
+                branches,
                 else_stmt,
             } => {
-                if let Object::Bool(true) = condition.evaluate(env)? {
-                    return self.execute(then_stmt, env);
-                } else if let Some(else_stmt) = else_stmt {
+                for (condition, body) in branches {
+                    if let Object::Bool(true) = condition.evaluate(env)? {
+                        return self.execute(body, env);
+                    }
+                }
+                if let Some(else_stmt) = else_stmt {
                     return self.execute(else_stmt, env);
                 }
             }
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+            } => return self.switch(subject, cases, default.as_deref(), env),
             Stmt::While {
                 condition,
                 body,
                 increment,
-            } => {
-                while condition.evaluate(env)?.into() {
-                    if let Some(signal) = self.execute(body, env)? {
-                        match signal {
-                            Signal::Break => break,
-                            Signal::Continue => {
-                                if let Some(increment) = increment {
-                                    increment.evaluate(env)?;
-                                }
-                                continue;
-                            }
-                            _ => return Ok(Some(signal)),
+                label,
+            } => return self.while_loop(condition, body, increment.as_ref(), label, env),
+            Stmt::DoWhile {
+                body,
+                condition,
+                label,
+            } => return self.do_while_loop(body, condition, label, env),
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                label,
+            } => return self.for_in_loop(*name, iterable, body, label, env),
+            Stmt::Break(label) => return Ok(Some(Signal::Break(label.clone()))),
+            Stmt::Continue(label) => return Ok(Some(Signal::Continue(label.clone()))),
+            Stmt::Return(Some(Expression::Call {
+                callee,
+                arguments,
+                line,
+            })) => {
+                let (callee, arguments) = prepare_call(callee, arguments, env)?;
+                return Ok(Some(Signal::TailCall {
+                    callee,
+                    arguments,
+                    line: *line,
+                }));
+            }
+            Stmt::Return(expression) => {
+                let value = match expression {
+                    Some(expression) => expression.evaluate(env)?,
+                    None => Object::Nil,
+                };
+                return Ok(Some(Signal::Return(value)));
+            }
+            Stmt::Class {
+                name,
+                methods,
+                static_methods,
+                getters,
+            } => define_class(*name, methods, static_methods, getters, env)?,
+            Stmt::Decorated { function, wrapped } => {
+                self.execute(function, env)?;
+                if let Stmt::Function { name, .. } = function.as_ref() {
+                    let value = wrapped.evaluate(env)?;
+                    env.define(*name, Some(value))?;
+                }
+            }
+            Stmt::Null => (),
+        }
+        Ok(None)
+    }
+
+    fn block(&self, block: &[Stmt], env: &mut Environment) -> LoxResult<Option<Signal>> {
+        env.enter_block();
+        let mut control = Ok(None);
+        for s in block {
+            control = self.execute(s, env);
+            if !matches!(control, Ok(None)) {
+                break;
+            }
+        }
+        env.exit_block();
+        control
+    }
+
+    fn switch(
+        &self,
+        subject: &Expression,
+        cases: &[(Expression, Stmt)],
+        default: Option<&Stmt>,
+        env: &mut Environment,
+    ) -> LoxResult<Option<Signal>> {
+        let subject = subject.evaluate(env)?;
+        for (value, body) in cases {
+            if value.evaluate(env)? == subject {
+                return self.execute(body, env);
+            }
+        }
+        match default {
+            Some(default) => self.execute(default, env),
+            None => Ok(None),
+        }
+    }
+
+    fn while_loop(
+        &self,
+        condition: &Expression,
+        body: &Stmt,
+        increment: Option<&Expression>,
+        label: &Option<String>,
+        env: &mut Environment,
+    ) -> LoxResult<Option<Signal>> {
+        while condition.evaluate(env)?.into() {
+            if let Some(signal) = self.execute(body, env)? {
+                match signal {
+                    Signal::Break(target) if targets(&target, label) => break,
+                    Signal::Continue(target) if targets(&target, label) => {
+                        if let Some(increment) = increment {
+                            increment.evaluate(env)?;
                         }
+                        continue;
                     }
+                    _ => return Ok(Some(signal)),
                 }
             }
-            Stmt::Break => return Ok(Some(Signal::Break)),
-            Stmt::Continue => return Ok(Some(Signal::Continue)),
-            Stmt::Return(expression) => return Ok(Some(Signal::Return(expression.clone()))),
-            Stmt::Class { name, methods } => {
-                let mut class_methods = HashMap::new();
-                for method in methods {
-                    if let Stmt::Function {
-                        name,
-                        body,
-                        parameters,
-                    } = method
-                    {
-                        class_methods.insert(
-                            name.to_owned(),
-                            UserDefinedFunction::new(
-                                name.clone(),
-                                body.clone(),
-                                parameters.clone(),
-                                env.clone(),
-                            ),
-                        );
-                    } else {
-                        panic!("expected a function");
-                    }
+        }
+        Ok(None)
+    }
+
+    fn do_while_loop(
+        &self,
+        body: &Stmt,
+        condition: &Expression,
+        label: &Option<String>,
+        env: &mut Environment,
+    ) -> LoxResult<Option<Signal>> {
+        loop {
+            if let Some(signal) = self.execute(body, env)? {
+                match signal {
+                    Signal::Break(target) if targets(&target, label) => break,
+                    Signal::Continue(target) if targets(&target, label) => (),
+                    _ => return Ok(Some(signal)),
                 }
-                let cl = UserDefinedStruct::new(name.to_owned(), class_methods);
-                env.define(name, Some(Object::Callable(Rc::new(RefCell::new(cl)))));
             }
-            Stmt::Null => (),
+            if !bool::from(condition.evaluate(env)?) {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    fn for_in_loop(
+        &self,
+        name: Symbol,
+        iterable: &Expression,
+        body: &Stmt,
+        label: &Option<String>,
+        env: &mut Environment,
+    ) -> LoxResult<Option<Signal>> {
+        let elements = match iterable.evaluate(env)? {
+            Object::Array(array) => array.borrow().clone(),
+            Object::Str(s) => s.chars().map(|c| Object::Str(c.to_string())).collect(),
+            object => {
+                return Err(RuntimeError::build(format!(
+                    "{} is not iterable",
+                    object.r#type()
+                )))
+            }
+        };
+        for element in elements {
+            env.enter_block();
+            env.define(name, Some(element))?;
+            let signal = self.execute(body, env);
+            env.exit_block();
+            match signal? {
+                Some(Signal::Break(target)) if targets(&target, label) => break,
+                Some(Signal::Continue(target)) if targets(&target, label) => (),
+                None => (),
+                signal => return Ok(signal),
+            }
         }
         Ok(None)
     }
 
     pub fn interpret(
-        self,
+        &self,
         env: &mut Environment,
         statements: &[Stmt],
     ) -> LoxResult<Option<Signal>> {
         for statement in statements {
-            let exec = self.execute(statement, env);
-            match exec {
-                Err(e) => eprintln!("{e}"),
-                Ok(Some(signal)) => {
-                    if let Signal::Return(_) = &signal {
-                        return Ok(Some(signal));
-                    } else {
-                        panic!("internal error");
-                    }
+            match self.execute(statement, env)? {
+                Some(signal @ (Signal::Return(_) | Signal::TailCall { .. })) => {
+                    return Ok(Some(signal))
                 }
-                _ => (),
+                // the parser only allows them in loops, which catch them
+                Some(Signal::Break(_)) => {
+                    return Err(RuntimeError::build("`break` outside loop".to_string()))
+                }
+                Some(Signal::Continue(_)) => {
+                    return Err(RuntimeError::build("`continue` outside loop".to_string()))
+                }
+                None => (),
             }
         }
         Ok(None)
     }
+
+    /// Interprets the statements, then returns the value of the last one if it is
+    /// an expression, as echoed by the REPL.
+    pub fn interpret_value(
+        &self,
+        env: &mut Environment,
+        statements: &[Stmt],
+    ) -> LoxResult<Option<Object>> {
+        let Some((last @ Stmt::Expr(expression), rest)) = statements.split_last() else {
+            self.interpret(env, statements)?;
+            return Ok(None);
+        };
+        self.interpret(env, rest)?;
+        self.trace(last);
+        expression.evaluate(env).map(Some)
+    }
 }
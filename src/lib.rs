@@ -1,57 +1,156 @@
+pub mod dot;
 pub mod errors;
 pub mod expression;
 pub mod functions;
 pub mod interpreter;
+pub mod optimizer;
 pub mod parser;
 pub mod scanner;
+pub mod symbol;
 
-use crate::errors::LoxResult;
-use crate::interpreter::{Environment, Interpreter};
-use crate::parser::Parser;
+use crate::errors::{init_coloring, LoxError, LoxResult};
+use crate::expression::Object;
+use crate::interpreter::{Environment, Options};
+use crate::optimizer::eliminate_dead_branches;
+use crate::parser::{Parser, Stmt};
 use crate::scanner::Scanner;
+use crate::symbol::Symbol;
+use std::collections::HashSet;
+use std::io;
 use std::io::Write;
-use std::{io, process};
 
-pub fn run_source(source: &str) {
-    let mut env = Environment::new();
-    if let Err(e) = run(source.trim(), &mut env) {
-        eprintln!("{e}");
-        process::exit(65);
+/// Runs a program, returning the exit code of the process on failure or `exit`.
+pub fn run_source(source: &str, options: Options) -> Result<(), i32> {
+    init_coloring();
+    let mut env = Environment::with_options(options);
+    run(source, &mut env, &mut HashSet::new())
+        .map(|_| ())
+        .map_err(report)
+}
+
+/// Evaluates a source made of a single expression and prints its value.
+pub fn run_expression(source: &str, options: Options) -> Result<(), i32> {
+    init_coloring();
+    let mut env = Environment::with_options(options);
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens().map_err(report)?;
+    let mut parser = Parser::new(scanner.tokens);
+    let expression = parser.parse_expression().map_err(report)?;
+    let value = expression
+        .evaluate(&mut env)
+        .and_then(|value| value.display(&mut env))
+        .map_err(report)?;
+    println!("{value}");
+    Ok(())
+}
+
+/// Prints the tokens of the program, comments included, without parsing it.
+pub fn print_tokens(source: &str) -> Result<(), i32> {
+    init_coloring();
+    let mut scanner = Scanner::with_comments(source);
+    scanner.scan_tokens().map_err(report)?;
+    for token in scanner.tokens {
+        println!("{token:?}");
+    }
+    Ok(())
+}
+
+/// Prints the tree of the parsed program without running it.
+pub fn print_ast(source: &str) -> Result<(), i32> {
+    for statement in parse(source)? {
+        print!("{}", statement.tree());
+    }
+    Ok(())
+}
+
+/// Prints the parsed program as a Graphviz graph without running it.
+pub fn print_dot(source: &str) -> Result<(), i32> {
+    print!("{}", dot::to_dot(&parse(source)?));
+    Ok(())
+}
+
+fn parse(source: &str) -> Result<Vec<Stmt>, i32> {
+    init_coloring();
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens().map_err(report)?;
+    let mut parser = Parser::new(scanner.tokens);
+    parser.parse().map_err(report)
+}
+
+/// Prints the error, unless the program called `exit`, and returns the exit code of
+/// the process: 65 for an invalid program and 70 for a failure while running it.
+fn report(error: LoxError) -> i32 {
+    if let LoxError::Exit(code) = error {
+        return code;
+    }
+    eprintln!("{error}");
+    match error {
+        LoxError::Runtime(_) | LoxError::Internal(_) => 70,
+        _ => 65,
     }
 }
 
-pub fn run_prompt() {
-    let mut env = Environment::new();
+/// Runs the REPL until the end of the input, returning the exit code of the process
+/// if the program calls `exit`.
+pub fn run_prompt(options: Options) -> Result<(), i32> {
+    init_coloring();
+    let mut env = Environment::with_options(options.clone());
+    // kept across lines like the environment, so that later lines parse the infix
+    // functions declared by earlier ones
+    let mut infix_operators = HashSet::new();
     loop {
         print!("> ");
         io::stdout().flush().expect("could not flush output stream");
         let mut input = String::new();
-        io::stdin()
+        let read = io::stdin()
             .read_line(&mut input)
             .expect("could not read line");
-        if let Err(e) = run(input.trim(), &mut env) {
-            eprintln!("{e}");
+        if read == 0 {
+            break;
+        }
+        if input.trim() == ":reset" {
+            env = Environment::with_options(options.clone());
+            infix_operators.clear();
+            continue;
+        }
+        let before = options.show_changes.then(|| env.summary());
+        let value = match run(input.trim(), &mut env, &mut infix_operators) {
+            Ok(value) => value,
+            Err(LoxError::Exit(code)) => return Err(code),
+            Err(e) => {
+                eprintln!("{e}");
+                None
+            }
+        };
+        if let Some(before) = before {
+            for change in before.changes(&env.summary()) {
+                eprintln!("{change}");
+            }
+        }
+        // the last value is echoed and kept in `_`, like in Python, which is not
+        // reported as a change, unless the program made `_` a constant
+        if let Some(value) = value.filter(|value| *value != Object::Nil) {
+            println!("{}", value.repr());
+            if let Err(e) = env.define(Symbol::intern("_"), Some(value)) {
+                eprintln!("{e}");
+            }
         }
         input.clear();
     }
+    Ok(())
 }
 
-fn run(source: &str, env: &mut Environment) -> LoxResult<()> {
+/// Runs the source, returning the value of its last statement if it is an expression.
+/// The infix operators it declares are added to `infix_operators`.
+fn run(
+    source: &str,
+    env: &mut Environment,
+    infix_operators: &mut HashSet<Symbol>,
+) -> LoxResult<Option<Object>> {
     let mut scanner = Scanner::new(source);
     scanner.scan_tokens()?;
-    let mut parser = Parser::new(scanner.tokens);
-    let result = parser.parse();
-    match result {
-        Ok(statements) => {
-            let interpreter = Interpreter::new();
-            if let Err(why) = interpreter.interpret(env, &statements) {
-                eprintln!("{why}");
-                process::exit(1);
-            }
-        }
-        Err(e) => {
-            eprintln!("{e}");
-        }
-    }
-    Ok(())
+    let mut parser = Parser::new(scanner.tokens).with_infix_operators(infix_operators.clone());
+    let statements = eliminate_dead_branches(parser.parse()?);
+    infix_operators.clone_from(parser.infix_operators());
+    env.interpreter().interpret_value(env, &statements)
 }
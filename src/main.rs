@@ -1,32 +1,127 @@
+use rlox::interpreter::Options;
+use std::io::{IsTerminal, Read};
 use std::process;
-use std::{env, fs};
+use std::{env, fs, io};
+
+const USAGE: &str =
+    "Usage: rlox [--max-string-len <len>] [--protect-builtins] [--show-changes] [--trace] [--ast | --dot | --tokens] [-i | <filename> [args...] | -c <source> [args...] | -e <expression> [args...]]";
+
+/// What to do with the program: `--ast`, `--dot` and `--tokens` print it instead of
+/// running it.
+enum Mode {
+    Run,
+    Ast,
+    Dot,
+    Tokens,
+}
+
+/// The program to run, when not read from the standard input.
+enum Script {
+    File(String),
+    Source(String),
+    /// A single expression whose value is printed, given with `-e`.
+    Expression(String),
+}
 
 fn main() {
-    let args = env::args().collect::<Vec<String>>();
-    match args.len() {
-        1 => rlox::run_prompt(),
-        2 => {
-            let filename = &args[1];
-            let source = match fs::read_to_string(filename) {
+    let mut options = Options::default();
+    let mut mode = Mode::Run;
+    let mut interactive = false;
+    let mut script = None;
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--max-string-len" => {
+                let value = iter.next().unwrap_or_else(|| {
+                    eprintln!("missing value for {arg}");
+                    process::exit(64);
+                });
+                match value.parse() {
+                    Ok(len) => options.max_string_len = Some(len),
+                    Err(why) => {
+                        eprintln!("invalid value for {arg}: {why}");
+                        process::exit(64);
+                    }
+                }
+            }
+            "--protect-builtins" => options.protect_builtins = true,
+            "--show-changes" => options.show_changes = true,
+            "--trace" => options.trace = true,
+            "-i" | "--interactive" => interactive = true,
+            "--ast" => mode = Mode::Ast,
+            "--dot" => mode = Mode::Dot,
+            "--tokens" => mode = Mode::Tokens,
+            "-c" | "-e" => match iter.next() {
+                Some(source) if arg == "-c" => {
+                    script = Some(Script::Source(source));
+                    break;
+                }
+                Some(expression) => {
+                    script = Some(Script::Expression(expression));
+                    break;
+                }
+                None => {
+                    eprintln!("missing value for {arg}");
+                    process::exit(64);
+                }
+            },
+            _ if arg.starts_with('-') => {
+                eprintln!("invalid argument: {arg}");
+                eprintln!("{USAGE}");
+                process::exit(64);
+            }
+            _ => {
+                script = Some(Script::File(arg));
+                break;
+            }
+        }
+    }
+    // everything after the script belongs to the program, see `args()`
+    options.args = iter.collect();
+
+    let run = |source: &str| match mode {
+        Mode::Run => rlox::run_source(source, options.clone()),
+        Mode::Ast => rlox::print_ast(source),
+        Mode::Dot => rlox::print_dot(source),
+        Mode::Tokens => rlox::print_tokens(source),
+    };
+
+    let result = match script {
+        None if interactive || io::stdin().is_terminal() => {
+            if !matches!(mode, Mode::Run) {
+                eprintln!("--ast, --dot and --tokens require a script");
+                process::exit(64);
+            }
+            rlox::run_prompt(options)
+        }
+        None => {
+            let mut source = String::new();
+            if let Err(why) = io::stdin().read_to_string(&mut source) {
+                eprintln!("cannot read standard input: {why}");
+                process::exit(1);
+            }
+            run(&source)
+        }
+        Some(Script::File(filename)) => {
+            let source = match fs::read_to_string(&filename) {
                 Err(why) => {
                     eprintln!("cannot open {filename}: {why}");
                     process::exit(1);
                 }
                 Ok(source) => source,
             };
-            rlox::run_source(&source);
+            run(&source)
         }
-        3 => {
-            let option = &args[1];
-            if option != "-c" {
-                eprintln!("invalid argument: {option}");
-                process::exit(1);
+        Some(Script::Source(source)) => run(&source),
+        Some(Script::Expression(expression)) => {
+            if !matches!(mode, Mode::Run) {
+                eprintln!("--ast, --dot and --tokens cannot be used with -e");
+                process::exit(64);
             }
-            rlox::run_source(&args[2]);
-        }
-        _ => {
-            eprintln!("Usage: rlox [<filename> | -c <source>]");
-            process::exit(64);
+            rlox::run_expression(&expression, options)
         }
+    };
+    if let Err(code) = result {
+        process::exit(code);
     }
 }
@@ -0,0 +1,120 @@
+use crate::expression::{Expression, Object};
+use crate::parser::Stmt;
+
+/// Removes the branches of `if` statements whose condition is `true` or `false`, so
+/// `if (false) S else T` becomes `T` and `if (true) S else T` becomes `S`. In a
+/// chain of `else if`s, the branches after a true condition are removed too.
+pub fn eliminate_dead_branches(statements: Vec<Stmt>) -> Vec<Stmt> {
+    statements.into_iter().map(eliminate).collect()
+}
+
+fn eliminate(statement: Stmt) -> Stmt {
+    match statement {
+        Stmt::If {
+            branches,
+            mut else_stmt,
+        } => {
+            let mut live = vec![];
+            for (condition, body) in branches {
+                match literal(&condition) {
+                    Some(true) => {
+                        else_stmt = Some(Box::new(body));
+                        break;
+                    }
+                    Some(false) => (),
+                    None => live.push((condition, eliminate(body))),
+                }
+            }
+            let else_stmt = else_stmt.map(|else_stmt| eliminate(*else_stmt));
+            if live.is_empty() {
+                else_stmt.unwrap_or(Stmt::Null)
+            } else {
+                Stmt::If {
+                    branches: live,
+                    else_stmt: else_stmt.map(Box::new),
+                }
+            }
+        }
+        Stmt::Switch {
+            subject,
+            cases,
+            default,
+        } => Stmt::Switch {
+            subject,
+            cases: cases
+                .into_iter()
+                .map(|(value, body)| (value, eliminate(body)))
+                .collect(),
+            default: default.map(|default| Box::new(eliminate(*default))),
+        },
+        Stmt::Block(statements) => Stmt::Block(eliminate_dead_branches(statements)),
+        Stmt::While {
+            condition,
+            body,
+            increment,
+            label,
+        } => Stmt::While {
+            condition,
+            body: Box::new(eliminate(*body)),
+            increment,
+            label,
+        },
+        Stmt::DoWhile {
+            body,
+            condition,
+            label,
+        } => Stmt::DoWhile {
+            body: Box::new(eliminate(*body)),
+            condition,
+            label,
+        },
+        Stmt::ForIn {
+            name,
+            iterable,
+            body,
+            label,
+        } => Stmt::ForIn {
+            name,
+            iterable,
+            body: Box::new(eliminate(*body)),
+            label,
+        },
+        Stmt::Function {
+            name,
+            body,
+            parameters,
+            defaults,
+        } => Stmt::Function {
+            name,
+            body: eliminate_dead_branches(body.to_vec()).into(),
+            parameters,
+            defaults,
+        },
+        Stmt::Class {
+            name,
+            methods,
+            static_methods,
+            getters,
+        } => Stmt::Class {
+            name,
+            methods: eliminate_dead_branches(methods),
+            static_methods: eliminate_dead_branches(static_methods),
+            getters: eliminate_dead_branches(getters),
+        },
+        Stmt::Decorated { function, wrapped } => Stmt::Decorated {
+            function: Box::new(eliminate(*function)),
+            wrapped,
+        },
+        statement => statement,
+    }
+}
+
+/// Returns the value of a boolean literal condition, looking through parentheses.
+/// Other literals are left to the interpreter, which only takes a branch on `true`.
+fn literal(condition: &Expression) -> Option<bool> {
+    match condition {
+        Expression::Literal(Object::Bool(b)) => Some(*b),
+        Expression::Grouping(expression) => literal(expression),
+        _ => None,
+    }
+}
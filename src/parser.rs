@@ -1,59 +1,313 @@
 use crate::errors::{LoxResult, ParseError};
 use crate::expression::{Expression, Expression::*, Object};
 use crate::scanner::{Token, TokenType};
+use crate::symbol::Symbol;
+use std::collections::HashSet;
+use std::rc::Rc;
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Stmt {
     Var {
-        name: String,
+        name: Symbol,
         initializer: Option<Expression>,
     },
+    Const {
+        name: Symbol,
+        initializer: Expression,
+    },
     Expr(Expression),
     Block(Vec<Stmt>),
+    /// An `if` and the `else if`s chained to it: the first branch whose condition
+    /// holds is executed, or `else_stmt` if none does.
     If {
-        condition: Expression,
-        then_stmt: Box<Stmt>,
+        branches: Vec<(Expression, Stmt)>,
         else_stmt: Option<Box<Stmt>>,
     },
+    Switch {
+        subject: Expression,
+        cases: Vec<(Expression, Stmt)>,
+        default: Option<Box<Stmt>>,
+    },
     While {
         condition: Expression,
         body: Box<Stmt>,
         increment: Option<Expression>,
+        label: Option<String>,
     },
-    Break,
-    Continue,
+    DoWhile {
+        body: Box<Stmt>,
+        condition: Expression,
+        label: Option<String>,
+    },
+    ForIn {
+        name: Symbol,
+        iterable: Expression,
+        body: Box<Stmt>,
+        label: Option<String>,
+    },
+    Break(Option<String>),
+    Continue(Option<String>),
     Return(Option<Expression>),
+    /// The body, parameters and defaults are shared with the functions created
+    /// from the declaration, so that defining and calling them does not copy them.
     Function {
-        name: String,
-        body: Vec<Stmt>,
-        parameters: Vec<String>,
+        name: Symbol,
+        body: Rc<[Stmt]>,
+        parameters: Rc<[Symbol]>,
+        /// Default values of the trailing parameters.
+        defaults: Rc<[Expression]>,
     },
     Class {
-        name: String,
+        name: Symbol,
         methods: Vec<Stmt>,
+        /// Methods called on the class itself, declared with `static`.
+        static_methods: Vec<Stmt>,
+        /// Methods without parameters, called when reading the property of their name.
+        getters: Vec<Stmt>,
+    },
+    /// A function declaration whose name is then bound to `wrapped`, the call of
+    /// its decorators on it.
+    Decorated {
+        function: Box<Stmt>,
+        wrapped: Expression,
     },
     Null,
 }
 
+impl Stmt {
+    /// Labels a loop, looking through the block a `for` loop is desugared into.
+    fn set_label(&mut self, name: String) {
+        match self {
+            Stmt::While { label, .. } | Stmt::DoWhile { label, .. } | Stmt::ForIn { label, .. } => {
+                *label = Some(name)
+            }
+            Stmt::Block(statements) => {
+                if let Some(stmt) = statements.last_mut() {
+                    stmt.set_label(name);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Indented tree of the statement, one node per line, with expressions in
+    /// their `repr` form.
+    pub fn tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(0, &mut out);
+        out
+    }
+
+    fn write_tree(&self, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        let label = |label: &Option<String>| match label {
+            Some(label) => format!(" {label}"),
+            None => String::new(),
+        };
+        match self {
+            Stmt::Var { name, initializer } => match initializer {
+                Some(initializer) => {
+                    out.push_str(&format!("{indent}Var {name} = {}\n", initializer.repr()))
+                }
+                None => out.push_str(&format!("{indent}Var {name}\n")),
+            },
+            Stmt::Const { name, initializer } => {
+                out.push_str(&format!("{indent}Const {name} = {}\n", initializer.repr()))
+            }
+            Stmt::Expr(expression) => {
+                out.push_str(&format!("{indent}Expr {}\n", expression.repr()));
+            }
+            Stmt::Block(statements) => {
+                out.push_str(&format!("{indent}Block\n"));
+                statements.iter().for_each(|s| s.write_tree(depth + 1, out));
+            }
+            Stmt::If {
+                branches,
+                else_stmt,
+            } => {
+                for (i, (condition, body)) in branches.iter().enumerate() {
+                    let keyword = if i == 0 { "If" } else { "Else If" };
+                    out.push_str(&format!("{indent}{keyword} {}\n", condition.repr()));
+                    body.write_tree(depth + 1, out);
+                }
+                if let Some(else_stmt) = else_stmt {
+                    out.push_str(&format!("{indent}Else\n"));
+                    else_stmt.write_tree(depth + 1, out);
+                }
+            }
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+            } => {
+                out.push_str(&format!("{indent}Switch {}\n", subject.repr()));
+                for (value, body) in cases {
+                    out.push_str(&format!("{indent}  Case {}\n", value.repr()));
+                    body.write_tree(depth + 2, out);
+                }
+                if let Some(default) = default {
+                    out.push_str(&format!("{indent}  Default\n"));
+                    default.write_tree(depth + 2, out);
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                label: name,
+            } => {
+                out.push_str(&format!(
+                    "{indent}While{} {}",
+                    label(name),
+                    condition.repr()
+                ));
+                if let Some(increment) = increment {
+                    out.push_str(&format!(" then {}", increment.repr()));
+                }
+                out.push('\n');
+                body.write_tree(depth + 1, out);
+            }
+            Stmt::DoWhile {
+                body,
+                condition,
+                label: name,
+            } => {
+                out.push_str(&format!(
+                    "{indent}DoWhile{} {}\n",
+                    label(name),
+                    condition.repr()
+                ));
+                body.write_tree(depth + 1, out);
+            }
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                label: loop_label,
+            } => {
+                out.push_str(&format!(
+                    "{indent}ForIn{} {name} in {}\n",
+                    label(loop_label),
+                    iterable.repr()
+                ));
+                body.write_tree(depth + 1, out);
+            }
+            Stmt::Break(name) => out.push_str(&format!("{indent}Break{}\n", label(name))),
+            Stmt::Continue(name) => out.push_str(&format!("{indent}Continue{}\n", label(name))),
+            Stmt::Return(value) => match value {
+                Some(value) => out.push_str(&format!("{indent}Return {}\n", value.repr())),
+                None => out.push_str(&format!("{indent}Return\n")),
+            },
+            Stmt::Function {
+                name,
+                body,
+                parameters,
+                ..
+            } => {
+                out.push_str(&format!(
+                    "{indent}Function {name}({})\n",
+                    parameters
+                        .iter()
+                        .map(|p| p.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+                body.iter().for_each(|s| s.write_tree(depth + 1, out));
+            }
+            Stmt::Class {
+                name,
+                methods,
+                static_methods,
+                getters,
+            } => {
+                out.push_str(&format!("{indent}Class {name}\n"));
+                methods.iter().for_each(|s| s.write_tree(depth + 1, out));
+                for method in static_methods {
+                    out.push_str(&format!("{indent}  Static\n"));
+                    method.write_tree(depth + 2, out);
+                }
+                for getter in getters {
+                    out.push_str(&format!("{indent}  Getter\n"));
+                    getter.write_tree(depth + 2, out);
+                }
+            }
+            Stmt::Decorated { function, wrapped } => {
+                out.push_str(&format!("{indent}Decorated {}\n", wrapped.repr()));
+                function.write_tree(depth + 1, out);
+            }
+            Stmt::Null => out.push_str(&format!("{indent}Null\n")),
+        }
+    }
+}
+
+/// Default limit on how deeply expressions and statements can nest, to stay well
+/// within the native stack.
+pub const MAX_NESTING_DEPTH: usize = 100;
+
+/// Limit on the number of binary operators chained at one level of nesting, whose
+/// operations are as deep as the chain is long.
+const MAX_OPERATOR_CHAIN: usize = 500;
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     enclosing_loops: usize,
     enclosing_funcs: usize,
     enclosing_classes: usize,
+    nesting_depth: usize,
+    max_nesting_depth: usize,
+    /// The binary operators chained so far at the current level of nesting.
+    chained_operators: usize,
+    /// The names declared in each enclosing block, none at global scope.
+    scopes: Vec<HashSet<Symbol>>,
+    /// The local variable whose initializer is being parsed.
+    initializing: Option<Symbol>,
+    /// Whether a block expression encloses the statement being parsed, which
+    /// cannot jump out of it.
+    in_block_expression: bool,
+    infix_operators: HashSet<Symbol>,
+    labels: Vec<Symbol>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
+        Self::with_max_depth(tokens, MAX_NESTING_DEPTH)
+    }
+
+    /// A parser rejecting expressions or statements nested more than `max_depth`
+    /// levels deep.
+    pub fn with_max_depth(mut tokens: Vec<Token>, max_depth: usize) -> Self {
+        tokens.retain(|token| !matches!(token.r#type, TokenType::Comment(_)));
         Self {
             tokens,
             current: 0,
             enclosing_loops: 0,
             enclosing_funcs: 0,
             enclosing_classes: 0,
+            nesting_depth: 0,
+            max_nesting_depth: max_depth,
+            chained_operators: 0,
+            scopes: vec![],
+            initializing: None,
+            in_block_expression: false,
+            infix_operators: HashSet::new(),
+            labels: vec![],
         }
     }
 
+    /// A parser knowing the infix operators declared by earlier sources, such as
+    /// the previous lines of the REPL.
+    pub fn with_infix_operators(mut self, operators: HashSet<Symbol>) -> Self {
+        self.infix_operators = operators;
+        self
+    }
+
+    /// The infix operators declared so far, earlier sources included.
+    pub fn infix_operators(&self) -> &HashSet<Symbol> {
+        &self.infix_operators
+    }
+
     pub fn parse(&mut self) -> LoxResult<Vec<Stmt>> {
         let mut statements = vec![];
         while self.peek_type() != TokenType::Eof {
@@ -62,12 +316,28 @@ impl Parser {
         Ok(statements)
     }
 
+    /// Parses a source made of a single expression, without a trailing `;`.
+    pub fn parse_expression(&mut self) -> LoxResult<Expression> {
+        let expression = self.expression()?;
+        if self.peek_type() != TokenType::Eof {
+            return Err(ParseError::build(
+                self.peek(),
+                "expected end of expression".to_string(),
+            ));
+        }
+        Ok(expression)
+    }
+
     fn declaration(&mut self) -> LoxResult<Stmt> {
         let statement = match self.peek_type() {
             TokenType::Let => {
                 self.advance();
                 self.var_declaration()
             }
+            TokenType::Const => {
+                self.advance();
+                self.const_declaration()
+            }
             TokenType::Fn => {
                 self.enclosing_funcs += 1;
                 self.advance();
@@ -75,6 +345,15 @@ impl Parser {
                 self.enclosing_funcs -= 1;
                 res
             }
+            TokenType::At => self.decorated_function(),
+            TokenType::Infix => {
+                self.advance();
+                self.consume(TokenType::Fn, "expected `fn` after `infix`".to_string())?;
+                self.enclosing_funcs += 1;
+                let res = self.infix_function();
+                self.enclosing_funcs -= 1;
+                res
+            }
             TokenType::Class => {
                 self.enclosing_classes += 1;
                 self.advance();
@@ -84,21 +363,21 @@ impl Parser {
             }
             _ => self.statement(),
         };
-        statement.map_err(|e| {
+        statement.inspect_err(|_| {
             self.synchronize();
-            e
         })
     }
 
     fn var_declaration(&mut self) -> LoxResult<Stmt> {
         if let TokenType::Identifier(name) = self.peek_type() {
             self.advance();
+            self.declare(name)?;
             let mut initializer: Option<Expression> = None;
             if self.peek_type() == TokenType::Equal {
                 self.advance();
-                initializer = Some(self.expression()?);
+                initializer = Some(self.initializer(name)?);
             }
-            self.consume(
+            self.consume_after(
                 TokenType::Semicolon,
                 "expected `;` after variable declaration".to_string(),
             )?;
@@ -111,7 +390,38 @@ impl Parser {
         }
     }
 
+    fn const_declaration(&mut self) -> LoxResult<Stmt> {
+        let name = self.consume_identifier("expected constant name".to_string())?;
+        self.declare(name)?;
+        self.consume(
+            TokenType::Equal,
+            format!("expected `=` after `{name}`: constants must be initialized"),
+        )?;
+        let initializer = self.initializer(name)?;
+        self.consume_after(
+            TokenType::Semicolon,
+            "expected `;` after constant declaration".to_string(),
+        )?;
+        Ok(Stmt::Const { name, initializer })
+    }
+
+    /// Parses the initializer of `name`, which cannot read a local variable
+    /// being declared. Globals can, since they may already be defined.
+    fn initializer(&mut self, name: Symbol) -> LoxResult<Expression> {
+        if self.scopes.is_empty() {
+            return self.expression();
+        }
+        let outer = self.initializing.replace(name);
+        let initializer = self.expression();
+        self.initializing = outer;
+        initializer
+    }
+
     fn statement(&mut self) -> LoxResult<Stmt> {
+        self.nested("statement", Self::nested_statement)
+    }
+
+    fn nested_statement(&mut self) -> LoxResult<Stmt> {
         match self.peek_type() {
             TokenType::Semicolon => {
                 self.advance();
@@ -125,11 +435,22 @@ impl Parser {
                 self.advance();
                 self.if_statement()
             }
+            TokenType::Switch => {
+                self.advance();
+                self.switch_statement()
+            }
             TokenType::While => {
                 self.enclosing_loops += 1;
                 self.advance();
                 let res = self.while_statement();
+                self.enclosing_loops -= 1;
+                res
+            }
+            TokenType::Do => {
                 self.enclosing_loops += 1;
+                self.advance();
+                let res = self.do_while_statement();
+                self.enclosing_loops -= 1;
                 res
             }
             TokenType::For => {
@@ -139,39 +460,60 @@ impl Parser {
                 self.enclosing_loops -= 1;
                 res
             }
+            TokenType::Identifier(label) if self.peek_next_type() == TokenType::Colon => {
+                self.advance();
+                self.advance();
+                if !matches!(
+                    self.peek_type(),
+                    TokenType::While | TokenType::Do | TokenType::For
+                ) {
+                    return Err(ParseError::build(
+                        self.peek(),
+                        "expected a loop after label".to_string(),
+                    ));
+                }
+                self.labels.push(label);
+                let res = self.statement();
+                self.labels.pop();
+                let mut stmt = res?;
+                stmt.set_label(label.to_string());
+                Ok(stmt)
+            }
             TokenType::Break => {
                 if self.enclosing_loops == 0 {
                     return Err(ParseError::build(
                         self.peek(),
-                        "`break` outside loop".to_string(),
+                        self.outside("break", "loop"),
                     ));
                 }
                 self.advance();
-                self.consume(
+                let label = self.loop_label()?;
+                self.consume_after(
                     TokenType::Semicolon,
                     "expected `;` after `break`".to_string(),
                 )?;
-                Ok(Stmt::Break)
+                Ok(Stmt::Break(label))
             }
             TokenType::Continue => {
                 if self.enclosing_loops == 0 {
                     return Err(ParseError::build(
                         self.peek(),
-                        "`continue` outside loop".to_string(),
+                        self.outside("continue", "loop"),
                     ));
                 }
                 self.advance();
-                self.consume(
+                let label = self.loop_label()?;
+                self.consume_after(
                     TokenType::Semicolon,
                     "expected `;` after `continue`".to_string(),
                 )?;
-                Ok(Stmt::Continue)
+                Ok(Stmt::Continue(label))
             }
             TokenType::Return => {
                 if self.enclosing_funcs == 0 {
                     return Err(ParseError::build(
                         self.peek(),
-                        "`return` outside function".to_string(),
+                        self.outside("return", "function"),
                     ));
                 }
                 self.advance();
@@ -180,7 +522,7 @@ impl Parser {
                 } else {
                     None
                 };
-                self.consume(
+                self.consume_after(
                     TokenType::Semicolon,
                     "expected `;` after `return`".to_string(),
                 )?;
@@ -190,7 +532,52 @@ impl Parser {
         }
     }
 
+    /// The error for a jump with nowhere to go, which may be out of a block
+    /// expression.
+    fn outside(&self, jump: &str, place: &str) -> String {
+        if self.in_block_expression {
+            format!("`{jump}` cannot leave a block expression")
+        } else {
+            format!("`{jump}` outside {place}")
+        }
+    }
+
+    fn loop_label(&mut self) -> LoxResult<Option<String>> {
+        if let TokenType::Identifier(label) = self.peek_type() {
+            if !self.labels.contains(&label) {
+                return Err(ParseError::build(
+                    self.peek(),
+                    format!("no enclosing loop labeled `{label}`"),
+                ));
+            }
+            self.advance();
+            Ok(Some(label.to_string()))
+        } else {
+            Ok(None)
+        }
+    }
+
     fn block(&mut self) -> LoxResult<Vec<Stmt>> {
+        self.scopes.push(HashSet::new());
+        let statements = self.block_statements();
+        self.scopes.pop();
+        statements
+    }
+
+    /// Records the declaration of a local variable, which cannot be declared twice
+    /// in the same block. Globals can, to redefine them in the REPL.
+    fn declare(&mut self, name: Symbol) -> LoxResult<()> {
+        let declared = self.scopes.last_mut().map(|scope| !scope.insert(name));
+        if declared == Some(true) {
+            return Err(ParseError::build(
+                self.previous().unwrap(),
+                format!("`{name}` already declared in this scope"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn block_statements(&mut self) -> LoxResult<Vec<Stmt>> {
         let mut statements = vec![];
 
         while self.peek_type() != TokenType::Eof && self.peek_type() != TokenType::RightBrace {
@@ -204,6 +591,104 @@ impl Parser {
         Ok(statements)
     }
 
+    /// Parses a block in expression position, whose last expression is not followed
+    /// by a `;` and gives its value, or `nil` if there is none. The `{` is consumed.
+    fn block_expression(&mut self) -> LoxResult<Expression> {
+        // neither the variable being initialized nor the loops and function around
+        // the expression are visible from its statements
+        let initializing = self.initializing.take();
+        let enclosing_loops = std::mem::take(&mut self.enclosing_loops);
+        let enclosing_funcs = std::mem::take(&mut self.enclosing_funcs);
+        let labels = std::mem::take(&mut self.labels);
+        let in_block_expression = std::mem::replace(&mut self.in_block_expression, true);
+        self.scopes.push(HashSet::new());
+        let block = self.block_expression_body();
+        self.scopes.pop();
+        self.initializing = initializing;
+        self.enclosing_loops = enclosing_loops;
+        self.enclosing_funcs = enclosing_funcs;
+        self.labels = labels;
+        self.in_block_expression = in_block_expression;
+        block
+    }
+
+    fn block_expression_body(&mut self) -> LoxResult<Expression> {
+        let mut statements = vec![];
+        let mut value = Expression::Literal(Object::Nil);
+        while self.peek_type() != TokenType::Eof && self.peek_type() != TokenType::RightBrace {
+            if self.starts_statement() {
+                statements.push(self.declaration()?);
+                continue;
+            }
+            let expression = self.expression()?;
+            if self.peek_type() == TokenType::RightBrace {
+                value = expression;
+                break;
+            }
+            self.consume_after(
+                TokenType::Semicolon,
+                "expected `;` or `}` after expression".to_string(),
+            )?;
+            statements.push(Stmt::Expr(expression));
+        }
+        self.consume(
+            TokenType::RightBrace,
+            "expected `}` after block".to_string(),
+        )?;
+        Ok(Expression::Block(statements, Box::new(value)))
+    }
+
+    /// Whether the next token starts a declaration or a statement other than an
+    /// expression statement.
+    fn starts_statement(&self) -> bool {
+        match self.peek_type() {
+            TokenType::Identifier(_) if self.peek_next_type() == TokenType::Colon => matches!(
+                self.tokens.get(self.current + 2).map(|token| &token.r#type),
+                Some(TokenType::While | TokenType::Do | TokenType::For)
+            ),
+            TokenType::Let
+            | TokenType::Const
+            | TokenType::Fn
+            | TokenType::At
+            | TokenType::Infix
+            | TokenType::Class
+            | TokenType::Semicolon
+            | TokenType::LeftBrace
+            | TokenType::If
+            | TokenType::Switch
+            | TokenType::While
+            | TokenType::Do
+            | TokenType::For
+            | TokenType::Break
+            | TokenType::Continue
+            | TokenType::Return => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the `{` just consumed opens a block expression rather than a map. A
+    /// map is empty or has a `:` after its first key, where a block has a statement
+    /// or a `;`.
+    fn block_ahead(&self) -> bool {
+        if self.starts_statement() {
+            return true;
+        }
+        let mut depth = 0usize;
+        for (i, token) in self.tokens[self.current..].iter().enumerate() {
+            match token.r#type {
+                TokenType::LeftParen | TokenType::LeftBracket | TokenType::LeftBrace => depth += 1,
+                TokenType::RightParen | TokenType::RightBracket => depth = depth.saturating_sub(1),
+                TokenType::RightBrace if depth > 0 => depth -= 1,
+                TokenType::RightBrace => return i > 0,
+                TokenType::Colon if depth == 0 => return false,
+                TokenType::Semicolon if depth == 0 => return true,
+                TokenType::Eof => return false,
+                _ => (),
+            }
+        }
+        false
+    }
+
     fn function(&mut self, kind: &str) -> LoxResult<Stmt> {
         let name = self.consume_identifier(format!("expected {kind} name"))?;
         self.consume(
@@ -211,10 +696,26 @@ impl Parser {
             format!("expected `(` after {kind} name"),
         )?;
         let mut parameters = vec![];
+        let mut defaults = vec![];
         if self.peek_type() != TokenType::RightParen {
             loop {
+                if self.peek_type() == TokenType::Comma {
+                    return Err(ParseError::build(
+                        self.peek(),
+                        "expected parameter before `,`".to_string(),
+                    ));
+                }
                 let parameter = self.consume_identifier("expected parameter name".to_string())?;
                 parameters.push(parameter);
+                if let TokenType::Equal = self.peek_type() {
+                    self.advance();
+                    defaults.push(self.expression()?);
+                } else if !defaults.is_empty() {
+                    return Err(ParseError::build(
+                        self.previous().unwrap(),
+                        "parameters without a default value must come first".to_string(),
+                    ));
+                }
                 if parameters.len() >= 255 {
                     return Err(ParseError::build(
                         self.previous().unwrap(),
@@ -232,18 +733,92 @@ impl Parser {
             TokenType::RightParen,
             "expected `)` after parameters".to_string(),
         )?;
+        let body = self.function_body(kind)?;
+        Ok(Stmt::Function {
+            name,
+            parameters: parameters.into(),
+            defaults: defaults.into(),
+            body: body.into(),
+        })
+    }
+
+    fn getter(&mut self) -> LoxResult<Stmt> {
+        let name = self.consume_identifier("expected getter name".to_string())?;
+        let body = self.function_body("getter")?;
+        Ok(Stmt::Function {
+            name,
+            parameters: Rc::new([]),
+            defaults: Rc::new([]),
+            body: body.into(),
+        })
+    }
+
+    fn function_body(&mut self, kind: &str) -> LoxResult<Vec<Stmt>> {
         self.consume(
             TokenType::LeftBrace,
             format!("expected `{{` before {kind} body"),
         )?;
-        let body = self.block()?;
-        Ok(Stmt::Function {
-            name,
-            parameters,
-            body,
+        // the body is not part of the loops around the declaration
+        let enclosing_loops = std::mem::take(&mut self.enclosing_loops);
+        let labels = std::mem::take(&mut self.labels);
+        let in_block_expression = std::mem::take(&mut self.in_block_expression);
+        let body = self.block();
+        self.enclosing_loops = enclosing_loops;
+        self.labels = labels;
+        self.in_block_expression = in_block_expression;
+        body
+    }
+
+    fn decorated_function(&mut self) -> LoxResult<Stmt> {
+        let mut decorators = vec![];
+        while self.peek_type() == TokenType::At {
+            self.advance();
+            let line = self.peek().line;
+            let name = self.consume_identifier("expected decorator name after `@`".to_string())?;
+            decorators.push(Variable { name, line });
+        }
+        let line = self.peek().line;
+        self.consume(TokenType::Fn, "expected `fn` after decorator".to_string())?;
+        self.enclosing_funcs += 1;
+        let function = self.function("function");
+        self.enclosing_funcs -= 1;
+        let function = function?;
+        let Stmt::Function { name, .. } = &function else {
+            unreachable!("function() returns a function declaration");
+        };
+        // `@a @b fn f` binds `f` to `a(b(f))`
+        let mut wrapped = Variable { name: *name, line };
+        for decorator in decorators.into_iter().rev() {
+            wrapped = Call {
+                callee: Box::new(decorator),
+                arguments: vec![wrapped],
+                line,
+            };
+        }
+        Ok(Stmt::Decorated {
+            function: Box::new(function),
+            wrapped,
         })
     }
 
+    fn infix_function(&mut self) -> LoxResult<Stmt> {
+        let token = self.peek();
+        let function = self.function("infix function")?;
+        if let Stmt::Function {
+            name, parameters, ..
+        } = &function
+        {
+            if parameters.len() != 2 {
+                return Err(ParseError::build(
+                    token,
+                    "infix functions take exactly 2 parameters".to_string(),
+                ));
+            }
+            self.infix_operators.insert(*name);
+        }
+        Ok(function)
+    }
+
     fn class_declaration(&mut self) -> LoxResult<Stmt> {
         let name = self.consume_identifier("expected class name".to_string())?;
         self.consume(
@@ -251,38 +826,170 @@ impl Parser {
             "expected `{` after class name".to_string(),
         )?;
         let mut methods = vec![];
+        let mut static_methods = vec![];
+        let mut getters = vec![];
         while !matches!(self.peek_type(), TokenType::RightBrace | TokenType::Eof) {
+            let is_static = self.peek_type() == TokenType::Static;
+            if is_static {
+                self.advance();
+            }
             self.consume(
                 TokenType::Fn,
                 "expected `fn` before method definition".to_string(),
             )?;
-            methods.push(self.function("method")?);
+            // a method without a parameter list is a getter
+            let is_getter = !is_static && self.peek_next_type() == TokenType::LeftBrace;
+            self.enclosing_funcs += 1;
+            let method = if is_getter {
+                self.getter()
+            } else {
+                self.function("method")
+            };
+            self.enclosing_funcs -= 1;
+            let method = method?;
+            if is_getter {
+                getters.push(method);
+            } else if is_static {
+                static_methods.push(method);
+            } else {
+                methods.push(method);
+            }
         }
         self.consume(
             TokenType::RightBrace,
             "expected `}` after class body".to_string(),
         )?;
 
-        Ok(Stmt::Class { name, methods })
+        Ok(Stmt::Class {
+            name,
+            methods,
+            static_methods,
+            getters,
+        })
     }
 
     fn if_statement(&mut self) -> LoxResult<Stmt> {
-        self.consume(TokenType::LeftParen, "expected `(` after `if`".to_string())?;
-        let condition = self.expression()?;
-        self.consume(TokenType::RightParen, "expected `)` after `if`".to_string())?;
-        let then_stmt = Box::new(self.statement()?);
+        let mut branches = vec![self.if_branch()?];
         let mut else_stmt = None;
-        if let TokenType::Else = self.peek_type() {
+        while let TokenType::Else = self.peek_type() {
             self.advance();
-            else_stmt = Some(Box::new(self.statement()?));
+            if let TokenType::If = self.peek_type() {
+                self.advance();
+                branches.push(self.if_branch()?);
+            } else {
+                else_stmt = Some(Box::new(self.statement()?));
+                break;
+            }
         }
         Ok(Stmt::If {
-            condition,
-            then_stmt,
+            branches,
             else_stmt,
         })
     }
 
+    fn if_branch(&mut self) -> LoxResult<(Expression, Stmt)> {
+        self.consume(TokenType::LeftParen, "expected `(` after `if`".to_string())?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "expected `)` after `if`".to_string())?;
+        Ok((condition, self.statement()?))
+    }
+
+    fn switch_statement(&mut self) -> LoxResult<Stmt> {
+        self.consume(
+            TokenType::LeftParen,
+            "expected `(` after `switch`".to_string(),
+        )?;
+        let subject = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "expected `)` after `switch`".to_string(),
+        )?;
+        self.consume(
+            TokenType::LeftBrace,
+            "expected `{` before switch body".to_string(),
+        )?;
+        let mut cases = vec![];
+        let mut default = None;
+        loop {
+            match self.peek_type() {
+                TokenType::Case => {
+                    self.advance();
+                    let value = self.expression()?;
+                    self.consume(
+                        TokenType::Colon,
+                        "expected `:` after case value".to_string(),
+                    )?;
+                    cases.push((value, self.case_body()?));
+                }
+                TokenType::Default => {
+                    if default.is_some() {
+                        return Err(ParseError::build(
+                            self.peek(),
+                            "duplicate `default` in switch".to_string(),
+                        ));
+                    }
+                    self.advance();
+                    self.consume(TokenType::Colon, "expected `:` after `default`".to_string())?;
+                    default = Some(Box::new(self.case_body()?));
+                }
+                _ => break,
+            }
+        }
+        self.consume(
+            TokenType::RightBrace,
+            "expected `}` after switch body".to_string(),
+        )?;
+        Ok(Stmt::Switch {
+            subject,
+            cases,
+            default,
+        })
+    }
+
+    fn case_body(&mut self) -> LoxResult<Stmt> {
+        self.scopes.push(HashSet::new());
+        let statements = self.case_statements();
+        self.scopes.pop();
+        statements.map(Stmt::Block)
+    }
+
+    fn case_statements(&mut self) -> LoxResult<Vec<Stmt>> {
+        let mut statements = vec![];
+        while !matches!(
+            self.peek_type(),
+            TokenType::Case | TokenType::Default | TokenType::RightBrace | TokenType::Eof
+        ) {
+            statements.push(self.declaration()?);
+        }
+        Ok(statements)
+    }
+
+    fn do_while_statement(&mut self) -> LoxResult<Stmt> {
+        let body = Box::new(self.statement()?);
+        self.consume(
+            TokenType::While,
+            "expected `while` after `do` body".to_string(),
+        )?;
+        self.consume(
+            TokenType::LeftParen,
+            "expected `(` after `while`".to_string(),
+        )?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "expected `)` after `while`".to_string(),
+        )?;
+        self.consume_after(
+            TokenType::Semicolon,
+            "expected `;` after `do` loop".to_string(),
+        )?;
+        Ok(Stmt::DoWhile {
+            body,
+            condition,
+            label: None,
+        })
+    }
+
     fn while_statement(&mut self) -> LoxResult<Stmt> {
         self.consume(
             TokenType::LeftParen,
@@ -298,15 +1005,38 @@ impl Parser {
             condition,
             body: stmt,
             increment: None,
+            label: None,
         })
     }
 
     fn for_statement(&mut self) -> LoxResult<Stmt> {
         self.consume(TokenType::LeftParen, "expected `(` after `for`".to_string())?;
+        if let (TokenType::Identifier(name), TokenType::In) =
+            (self.peek_type(), self.peek_next_type())
+        {
+            self.advance();
+            self.advance();
+            let iterable = self.expression()?;
+            self.consume(
+                TokenType::RightParen,
+                "expected `)` after for clauses".to_string(),
+            )?;
+            let body = Box::new(self.statement()?);
+            return Ok(Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                label: None,
+            });
+        }
         let initializer = match self.peek_type() {
             TokenType::Let => {
                 self.advance();
-                Some(Box::new(self.var_declaration()?))
+                // the loop variable is local to the block the loop desugars to
+                self.scopes.push(HashSet::new());
+                let declaration = self.var_declaration();
+                self.scopes.pop();
+                Some(Box::new(declaration?))
             }
             TokenType::Semicolon => {
                 self.advance();
@@ -336,6 +1066,7 @@ impl Parser {
             condition,
             body: Box::new(Stmt::Block(while_body)),
             increment,
+            label: None,
         });
         Ok(Stmt::Block(statements))
     }
@@ -357,7 +1088,7 @@ impl Parser {
 
     fn expr_statement(&mut self) -> LoxResult<Stmt> {
         let expr = self.expression()?;
-        self.consume(
+        self.consume_after(
             TokenType::Semicolon,
             "expected `;` after expression".to_string(),
         )?;
@@ -365,36 +1096,100 @@ impl Parser {
     }
 
     fn expression(&mut self) -> LoxResult<Expression> {
-        self.assignment()
+        self.nested("expression", Self::assignment)
+    }
+
+    /// Parses one more level of nesting, failing once the maximum depth is reached.
+    fn nested<T>(
+        &mut self,
+        what: &str,
+        parse: impl FnOnce(&mut Self) -> LoxResult<T>,
+    ) -> LoxResult<T> {
+        if self.nesting_depth >= self.max_nesting_depth {
+            return Err(ParseError::build(
+                self.peek(),
+                format!("{what} nesting too deep"),
+            ));
+        }
+        self.nesting_depth += 1;
+        let chained_operators = self.chained_operators;
+        let parsed = parse(self);
+        self.chained_operators = chained_operators;
+        self.nesting_depth -= 1;
+        parsed
+    }
+
+    /// Consumes the next operator of a chain of binary operations, failing once
+    /// the chain gets too long.
+    fn chain_operator(&mut self) -> LoxResult<Token> {
+        self.advance();
+        let op = self.previous().unwrap();
+        if self.chained_operators >= MAX_OPERATOR_CHAIN {
+            return Err(ParseError::build(
+                op,
+                "expression nesting too deep".to_string(),
+            ));
+        }
+        self.chained_operators += 1;
+        Ok(op)
     }
 
     fn assignment(&mut self) -> LoxResult<Expression> {
-        let expr = self.or()?;
+        let expr = self.coalesce()?;
         if self.peek_type() == TokenType::Equal {
+            let equals = self.peek();
             self.advance();
             let value = self.assignment()?;
             match expr {
-                Variable(name) => Ok(Assign(name, Box::new(value))),
+                Variable { name, .. } => Ok(Assign(name, Box::new(value))),
                 Get { name, object } => Ok(Set {
                     object,
                     name,
                     value: Box::new(value),
                 }),
-                _ => Err(ParseError::build(
-                    self.peek(),
-                    "invalid assignment target".to_string(),
-                )),
+                Index { object, index } => Ok(SetIndex {
+                    object,
+                    index,
+                    value: Box::new(value),
+                }),
+                target => {
+                    let target = match target {
+                        Literal(_) | Array(_) | Map(_) => "a literal",
+                        Call { .. } => "a function call",
+                        Unary { .. } | Binary { .. } | Logical { .. } => "an operation",
+                        Grouping(_) => "a parenthesized expression",
+                        This => "`this`",
+                        _ => "this expression",
+                    };
+                    Err(ParseError::build(
+                        equals,
+                        format!("invalid assignment target: cannot assign to {target}"),
+                    ))
+                }
             }
         } else {
             Ok(expr)
         }
     }
 
+    fn coalesce(&mut self) -> LoxResult<Expression> {
+        let mut expr = self.or()?;
+        while let TokenType::QuestionQuestion = self.peek_type() {
+            let op = self.chain_operator()?;
+            let right = self.or()?;
+            expr = Logical {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            }
+        }
+        Ok(expr)
+    }
+
     fn or(&mut self) -> LoxResult<Expression> {
         let mut expr = self.and()?;
-        while let TokenType::Or = self.peek_type() {
-            self.advance();
-            let op = self.previous().unwrap();
+        while let TokenType::Or | TokenType::Xor = self.peek_type() {
+            let op = self.chain_operator()?;
             let right = self.and()?;
             expr = Logical {
                 left: Box::new(expr),
@@ -408,8 +1203,7 @@ impl Parser {
     fn and(&mut self) -> LoxResult<Expression> {
         let mut expr = self.equality()?;
         while let TokenType::And = self.peek_type() {
-            self.advance();
-            let op = self.previous().unwrap();
+            let op = self.chain_operator()?;
             let right = self.equality()?;
             expr = Logical {
                 left: Box::new(expr),
@@ -421,13 +1215,54 @@ impl Parser {
     }
 
     fn equality(&mut self) -> LoxResult<Expression> {
-        let mut expr = self.comparison()?;
+        let mut expr = self.bit_or()?;
         while matches!(
             self.peek_type(),
             TokenType::BangEqual | TokenType::EqualEqual
         ) {
-            self.advance();
-            let op = self.previous().unwrap();
+            let op = self.chain_operator()?;
+            let right = self.bit_or()?;
+            expr = Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn bit_or(&mut self) -> LoxResult<Expression> {
+        let mut expr = self.bit_xor()?;
+        while let TokenType::Pipe = self.peek_type() {
+            let op = self.chain_operator()?;
+            let right = self.bit_xor()?;
+            expr = Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> LoxResult<Expression> {
+        let mut expr = self.bit_and()?;
+        while let TokenType::Caret = self.peek_type() {
+            let op = self.chain_operator()?;
+            let right = self.bit_and()?;
+            expr = Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> LoxResult<Expression> {
+        let mut expr = self.comparison()?;
+        while let TokenType::Ampersand = self.peek_type() {
+            let op = self.chain_operator()?;
             let right = self.comparison()?;
             expr = Binary {
                 left: Box::new(expr),
@@ -439,13 +1274,50 @@ impl Parser {
     }
 
     fn comparison(&mut self) -> LoxResult<Expression> {
+        let mut expr = self.infix()?;
+        while matches!(
+            self.peek_type(),
+            TokenType::Less
+                | TokenType::LessEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual
+                | TokenType::Instanceof
+        ) {
+            let op = self.chain_operator()?;
+            let right = self.infix()?;
+            expr = Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn infix(&mut self) -> LoxResult<Expression> {
+        let mut expr = self.shift()?;
+        while let TokenType::Identifier(name) = self.peek_type() {
+            if !self.infix_operators.contains(&name) {
+                break;
+            }
+            let line = self.chain_operator()?.line;
+            let right = self.shift()?;
+            expr = Call {
+                callee: Box::new(Variable { name, line }),
+                arguments: vec![expr, right],
+                line,
+            };
+        }
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> LoxResult<Expression> {
         let mut expr = self.term()?;
         while matches!(
             self.peek_type(),
-            TokenType::Less | TokenType::LessEqual | TokenType::Greater | TokenType::GreaterEqual
+            TokenType::LessLess | TokenType::GreaterGreater
         ) {
-            self.advance();
-            let op = self.previous().unwrap();
+            let op = self.chain_operator()?;
             let right = self.term()?;
             expr = Binary {
                 left: Box::new(expr),
@@ -459,8 +1331,7 @@ impl Parser {
     fn term(&mut self) -> LoxResult<Expression> {
         let mut expr = self.factor()?;
         while matches!(self.peek_type(), TokenType::Minus | TokenType::Plus) {
-            self.advance();
-            let op = self.previous().unwrap();
+            let op = self.chain_operator()?;
             let right = self.factor()?;
             expr = Binary {
                 left: Box::new(expr),
@@ -473,9 +1344,11 @@ impl Parser {
 
     fn factor(&mut self) -> LoxResult<Expression> {
         let mut expr = self.unary()?;
-        while matches!(self.peek_type(), TokenType::Slash | TokenType::Star) {
-            self.advance();
-            let op = self.previous().unwrap();
+        while matches!(
+            self.peek_type(),
+            TokenType::Slash | TokenType::Star | TokenType::Percent
+        ) {
+            let op = self.chain_operator()?;
             let right = self.unary()?;
             expr = Binary {
                 left: Box::new(expr),
@@ -487,16 +1360,46 @@ impl Parser {
     }
 
     fn unary(&mut self) -> LoxResult<Expression> {
-        if matches!(self.peek_type(), TokenType::Minus | TokenType::Bang) {
+        if matches!(
+            self.peek_type(),
+            TokenType::Minus | TokenType::Bang | TokenType::Tilde
+        ) {
             self.advance();
             let op = self.previous().unwrap();
-            let right = self.unary()?;
+            let right = self.nested("expression", Self::unary)?;
             Ok(Unary {
                 op,
                 right: Box::new(right),
             })
+        } else if matches!(
+            self.peek_type(),
+            TokenType::PlusPlus | TokenType::MinusMinus
+        ) {
+            self.advance();
+            let op = self.previous().unwrap();
+            let target = self.nested("expression", Self::unary)?;
+            increment(target, &op)
         } else {
-            self.call()
+            self.postfix()
+        }
+    }
+
+    fn postfix(&mut self) -> LoxResult<Expression> {
+        let expr = self.call()?;
+        if matches!(
+            self.peek_type(),
+            TokenType::PlusPlus | TokenType::MinusMinus
+        ) {
+            self.advance();
+            let op = self.previous().unwrap();
+            let (name, line) = update_target(expr, &op)?;
+            Ok(PostfixUpdate {
+                name,
+                op: update_operator(&op),
+                line,
+            })
+        } else {
+            Ok(expr)
         }
     }
 
@@ -513,9 +1416,34 @@ impl Parser {
                 let name =
                     self.consume_identifier("expected property name after `.`".to_string())?;
                 callee = Get {
-                    name,
+                    name: name.to_string(),
                     object: Box::new(callee),
                 }
+            } else if self.peek_type() == TokenType::LeftBracket {
+                self.advance();
+                let index = self.expression()?;
+                callee = if self.peek_type() == TokenType::Colon {
+                    self.advance();
+                    let end = self.expression()?;
+                    self.consume(
+                        TokenType::RightBracket,
+                        "expected `]` after slice".to_string(),
+                    )?;
+                    Slice {
+                        object: Box::new(callee),
+                        start: Box::new(index),
+                        end: Box::new(end),
+                    }
+                } else {
+                    self.consume(
+                        TokenType::RightBracket,
+                        "expected `]` after index".to_string(),
+                    )?;
+                    Index {
+                        object: Box::new(callee),
+                        index: Box::new(index),
+                    }
+                }
             } else {
                 break;
             };
@@ -525,9 +1453,10 @@ impl Parser {
     }
 
     fn finish_call(&mut self, callee: Expression) -> LoxResult<Expression> {
+        let line = self.previous().unwrap().line;
         let mut arguments = vec![];
         if self.peek_type() != TokenType::RightParen {
-            arguments.push(self.expression()?);
+            arguments.push(self.argument()?);
             while self.peek_type() == TokenType::Comma {
                 self.advance();
                 if arguments.len() >= 255 {
@@ -536,7 +1465,7 @@ impl Parser {
                         "can't have more than 255 arguments".to_string(),
                     ));
                 }
-                arguments.push(self.expression()?);
+                arguments.push(self.argument()?);
             }
         }
         self.consume(
@@ -546,9 +1475,53 @@ impl Parser {
         Ok(Call {
             callee: Box::new(callee),
             arguments,
+            line,
         })
     }
 
+    fn argument(&mut self) -> LoxResult<Expression> {
+        if self.peek_type() == TokenType::Comma {
+            return Err(ParseError::build(
+                self.peek(),
+                "expected argument before `,`".to_string(),
+            ));
+        }
+        self.expression()
+    }
+
+    /// Collects the literal parts and the expressions of an interpolated string.
+    fn interpolation(&mut self) -> LoxResult<Expression> {
+        let token = self.peek();
+        self.advance();
+        let TokenType::Interpolation(prefix) = token.r#type.clone() else {
+            unreachable!("called on an interpolation token");
+        };
+        let mut parts = vec![];
+        if !prefix.is_empty() {
+            parts.push(Literal(Object::Str(prefix)));
+        }
+        loop {
+            parts.push(self.expression()?);
+            let (literal, done) = match self.peek_type() {
+                TokenType::Interpolation(literal) => (literal, false),
+                TokenType::Str(literal) => (literal, true),
+                _ => {
+                    return Err(ParseError::build(
+                        self.peek(),
+                        "expected `}` after interpolated expression".to_string(),
+                    ))
+                }
+            };
+            self.advance();
+            if !literal.is_empty() {
+                parts.push(Literal(Object::Str(literal)));
+            }
+            if done {
+                return Ok(Interpolation(parts));
+            }
+        }
+    }
+
     fn primary(&mut self) -> LoxResult<Expression> {
         let token_type = self.peek_type();
         match token_type {
@@ -569,19 +1542,68 @@ impl Parser {
                 )?;
                 Ok(Grouping(Box::new(expr)))
             }
+            TokenType::Interpolation(_) => self.interpolation(),
             TokenType::Identifier(name) => {
+                if self.initializing.as_ref() == Some(&name) {
+                    return Err(ParseError::build(
+                        self.peek(),
+                        "cannot read local variable in its own initializer".to_string(),
+                    ));
+                }
+                let line = self.peek().line;
+                self.advance();
+                Ok(Variable { name, line })
+            }
+            TokenType::LeftBracket => {
+                self.advance();
+                let mut elements = vec![];
+                if self.peek_type() != TokenType::RightBracket {
+                    elements.push(self.expression()?);
+                    while self.peek_type() == TokenType::Comma {
+                        self.advance();
+                        elements.push(self.expression()?);
+                    }
+                }
+                self.consume(
+                    TokenType::RightBracket,
+                    "expected `]` after array elements".to_string(),
+                )?;
+                Ok(Array(elements))
+            }
+            TokenType::LeftBrace => {
                 self.advance();
-                Ok(Variable(name))
+                if self.block_ahead() {
+                    return self.block_expression();
+                }
+                let mut entries = vec![];
+                while self.peek_type() != TokenType::RightBrace {
+                    let key = self.expression()?;
+                    self.consume(TokenType::Colon, "expected `:` after map key".to_string())?;
+                    entries.push((key, self.expression()?));
+                    if self.peek_type() == TokenType::Comma {
+                        self.advance();
+                    } else {
+                        break;
+                    }
+                }
+                self.consume(
+                    TokenType::RightBrace,
+                    "expected `}` after map entries".to_string(),
+                )?;
+                Ok(Map(entries))
             }
             TokenType::This => {
                 self.advance();
-                if self.enclosing_loops == 0 {
+                if self.enclosing_classes == 0 {
                     Err(ParseError::build(
                         self.peek(),
                         "`this` outside class".to_string(),
                     ))
                 } else {
-                    Ok(Variable("this".to_string()))
+                    Ok(Variable {
+                        name: Symbol::intern("this"),
+                        line: self.previous().unwrap().line,
+                    })
                 }
             }
             _ => Err(ParseError::build(
@@ -600,7 +1622,18 @@ impl Parser {
         }
     }
 
-    fn consume_identifier(&mut self, message: String) -> LoxResult<String> {
+    /// Like `consume`, but a missing token is reported after the previous one, on
+    /// the line where it was expected rather than on the line of the next token.
+    fn consume_after(&mut self, token_type: TokenType, message: String) -> LoxResult<Token> {
+        if self.peek_type() == token_type {
+            self.advance();
+            Ok(self.peek())
+        } else {
+            Err(ParseError::after(self.previous().unwrap(), message))
+        }
+    }
+
+    fn consume_identifier(&mut self, message: String) -> LoxResult<Symbol> {
         if let TokenType::Identifier(name) = self.peek_type() {
             self.advance();
             Ok(name)
@@ -619,9 +1652,14 @@ impl Parser {
             match self.peek_type() {
                 TokenType::Class
                 | TokenType::Fn
+                | TokenType::Infix
+                | TokenType::At
                 | TokenType::Let
+                | TokenType::Const
+                | TokenType::Do
                 | TokenType::For
                 | TokenType::If
+                | TokenType::Switch
                 | TokenType::While
                 | TokenType::Return => return,
                 _ => self.advance(),
@@ -637,6 +1675,12 @@ impl Parser {
         self.peek().r#type
     }
 
+    fn peek_next_type(&self) -> TokenType {
+        self.tokens
+            .get(self.current + 1)
+            .map_or(TokenType::Eof, |token| token.r#type.clone())
+    }
+
     fn advance(&mut self) {
         if self.peek_type() != TokenType::Eof {
             self.current += 1;
@@ -647,3 +1691,38 @@ impl Parser {
         self.tokens.get(self.current - 1).cloned()
     }
 }
+
+/// Desugars `++target` and `--target` into `target = target ± 1`.
+fn increment(target: Expression, op: &Token) -> LoxResult<Expression> {
+    let (name, line) = update_target(target, op)?;
+    let value = Binary {
+        left: Box::new(Variable { name, line }),
+        op: update_operator(op),
+        right: Box::new(Literal(Object::Number(1.0))),
+    };
+    Ok(Assign(name, Box::new(value)))
+}
+
+/// The variable updated by `++` or `--`, with the line where it is read.
+fn update_target(target: Expression, op: &Token) -> LoxResult<(Symbol, usize)> {
+    match target {
+        Variable { name, line } => Ok((name, line)),
+        _ => Err(ParseError::build(
+            op.clone(),
+            format!("invalid `{}` target: expected a variable", op.lexeme),
+        )),
+    }
+}
+
+/// The `+` or `-` applied by `++` or `--`.
+fn update_operator(op: &Token) -> Token {
+    let (r#type, lexeme) = match op.r#type {
+        TokenType::PlusPlus => (TokenType::Plus, "+"),
+        _ => (TokenType::Minus, "-"),
+    };
+    Token {
+        r#type,
+        lexeme: lexeme.to_string(),
+        ..op.clone()
+    }
+}
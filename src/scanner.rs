@@ -1,5 +1,6 @@
 use crate::errors::{LoxError, LoxResult, ScanError, ScanErrorType};
 use crate::expression::{Expression, Expression::Literal, Object};
+use crate::symbol::Symbol;
 use phf::phf_map;
 use std::fmt::{Debug, Display, Formatter};
 use std::iter::Peekable;
@@ -7,37 +8,60 @@ use std::str::Chars;
 
 pub static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "and" => TokenType::And,
+    "case" => TokenType::Case,
     "class" => TokenType::Class,
+    "const" => TokenType::Const,
+    "default" => TokenType::Default,
+    "do" => TokenType::Do,
     "else" => TokenType::Else,
     "false" => TokenType::False,
     "for" => TokenType::For,
     "fn" => TokenType::Fn,
     "if" => TokenType::If,
+    "in" => TokenType::In,
+    "infix" => TokenType::Infix,
     "nil" => TokenType::Nil,
     "or" => TokenType::Or,
     "return" => TokenType::Return,
     "super" => TokenType::Super,
+    "switch" => TokenType::Switch,
     "this" => TokenType::This,
     "true" => TokenType::True,
     "let" => TokenType::Let,
     "while" => TokenType::While,
+    "xor" => TokenType::Xor,
+    "static" => TokenType::Static,
+    "instanceof" => TokenType::Instanceof,
     "break" => TokenType::Break,
     "continue" => TokenType::Continue,
 };
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenType {
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
     Dot,
     Minus,
     Plus,
+    MinusMinus,
+    PlusPlus,
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    QuestionQuestion,
+    At,
 
     Bang,
     BangEqual,
@@ -47,26 +71,42 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
 
-    Identifier(String),
+    Identifier(Symbol),
     Str(String),
+    /// Part of a string literal preceding a `${` interpolation.
+    Interpolation(String),
+    /// Text following `//`, only emitted by `Scanner::with_comments`.
+    Comment(String),
     Number(f64),
 
     And,
     Break,
+    Case,
     Class,
+    Const,
     Continue,
+    Default,
+    Do,
     Else,
     Fn,
     For,
     False,
     If,
+    In,
+    Infix,
+    Instanceof,
     Nil,
     Or,
     Return,
+    Static,
     Super,
+    Switch,
     This,
     True,
+    Xor,
     Let,
     While,
 
@@ -90,6 +130,7 @@ impl TryInto<Expression> for TokenType {
 }
 
 #[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub r#type: TokenType,
     pub lexeme: String,
@@ -118,14 +159,27 @@ pub struct Scanner<'a> {
     current: usize,
     line: usize,
     col: usize,
+    start_line: usize,
+    start_col: usize,
+    /// Number of unclosed braces inside each interpolation being scanned.
+    interpolations: Vec<usize>,
+    keep_comments: bool,
 }
 
 impl<'a> Scanner<'a> {
     pub fn scan_tokens(&mut self) -> LoxResult<()> {
         while self.peek().is_some() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_col = self.col;
             self.scan_token()?;
         }
+        if !self.interpolations.is_empty() {
+            return Err(self.scan_error(
+                ScanErrorType::UnterminatedString,
+                "missing `}` after interpolated expression",
+            ));
+        }
         self.add_eof_token();
         Ok(())
     }
@@ -135,14 +189,51 @@ impl<'a> Scanner<'a> {
         let r#type = match c {
             '(' => TokenType::LeftParen,
             ')' => TokenType::RightParen,
-            '{' => TokenType::LeftBrace,
-            '}' => TokenType::RightBrace,
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                TokenType::LeftBrace
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.string()?
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    TokenType::RightBrace
+                }
+                None => TokenType::RightBrace,
+            },
+            '[' => TokenType::LeftBracket,
+            ']' => TokenType::RightBracket,
             ',' => TokenType::Comma,
+            ':' => TokenType::Colon,
             '.' => TokenType::Dot,
-            '-' => TokenType::Minus,
-            '+' => TokenType::Plus,
+            '-' => {
+                if self.next_match('-') {
+                    TokenType::MinusMinus
+                } else {
+                    TokenType::Minus
+                }
+            }
+            '+' => {
+                if self.next_match('+') {
+                    TokenType::PlusPlus
+                } else {
+                    TokenType::Plus
+                }
+            }
             ';' => TokenType::Semicolon,
             '*' => TokenType::Star,
+            '%' => TokenType::Percent,
+            '&' => TokenType::Ampersand,
+            '|' => TokenType::Pipe,
+            '^' => TokenType::Caret,
+            '~' => TokenType::Tilde,
+            '@' => TokenType::At,
+            '?' if self.next_match('?') => TokenType::QuestionQuestion,
             '!' => {
                 if self.next_match('=') {
                     TokenType::BangEqual
@@ -160,6 +251,8 @@ impl<'a> Scanner<'a> {
             '>' => {
                 if self.next_match('=') {
                     TokenType::GreaterEqual
+                } else if self.next_match('>') {
+                    TokenType::GreaterGreater
                 } else {
                     TokenType::Greater
                 }
@@ -167,18 +260,25 @@ impl<'a> Scanner<'a> {
             '<' => {
                 if self.next_match('=') {
                     TokenType::LessEqual
+                } else if self.next_match('<') {
+                    TokenType::LessLess
                 } else {
                     TokenType::Less
                 }
             }
             '/' => {
                 if self.next_match('/') {
+                    let mut text = String::new();
                     while let Some(c) = self.peek() {
-                        if c == '\n' {
+                        if c == '\n' || (c == '\r' && self.peek_next() == Some('\n')) {
                             break;
                         }
+                        text.push(c);
                         self.advance();
                     }
+                    if self.keep_comments {
+                        self.add_token(TokenType::Comment(text));
+                    }
                     return Ok(());
                 }
                 TokenType::Slash
@@ -186,6 +286,11 @@ impl<'a> Scanner<'a> {
             ' ' | '\r' | '\t' | '\n' => {
                 return Ok(());
             }
+            '"' if self.peek() == Some('"') && self.peek_next() == Some('"') => {
+                self.advance();
+                self.advance();
+                self.triple_quoted_string()?
+            }
             '"' => self.string()?,
             x if x.is_ascii_digit() => self.number()?,
             c if c.is_ascii_alphabetic() || c == '_' => self.identifier()?,
@@ -202,12 +307,11 @@ impl<'a> Scanner<'a> {
 
     fn add_token(&mut self, r#type: TokenType) {
         let lexeme = self.source[self.start..self.current].to_owned();
-        let start = self.col - lexeme.len();
         self.tokens.push(Token {
             r#type,
             lexeme,
-            line: self.line,
-            col: start,
+            line: self.start_line,
+            col: self.start_col,
         });
     }
 
@@ -224,11 +328,23 @@ impl<'a> Scanner<'a> {
         self.stream.peek().cloned()
     }
 
+    fn peek_next(&self) -> Option<char> {
+        self.stream.clone().nth(1)
+    }
+
+    /// Whether the character just consumed is the `\r` of a `\r\n` line ending,
+    /// which is handled as a single `\n`.
+    fn at_crlf(&mut self, c: char) -> bool {
+        c == '\r' && self.peek() == Some('\n')
+    }
+
+    /// Consumes a character. `current` is a byte offset into the source, while
+    /// `col` counts characters, a tab being a single one.
     fn advance(&mut self) -> Option<char> {
-        self.current += 1;
-        self.col += 1;
         let next = self.stream.next();
         if let Some(c) = next {
+            self.current += c.len_utf8();
+            self.col += 1;
             if c == '\n' {
                 self.line += 1;
                 self.col = 1;
@@ -258,7 +374,14 @@ impl<'a> Scanner<'a> {
                 break;
             }
             let c = self.advance().unwrap();
-            s.push(c);
+            if c == '\\' {
+                s.push(self.escape()?);
+            } else if c == '$' && self.next_match('{') {
+                self.interpolations.push(0);
+                return Ok(TokenType::Interpolation(s));
+            } else if !self.at_crlf(c) {
+                s.push(c);
+            }
         }
         if self.peek().is_none() {
             return Err(self.scan_error(ScanErrorType::UnterminatedString, "missing \" delimiter"));
@@ -267,6 +390,88 @@ impl<'a> Scanner<'a> {
         Ok(TokenType::Str(s))
     }
 
+    /// Scans a `"""` string, which can contain unescaped quotes and newlines.
+    fn triple_quoted_string(&mut self) -> LoxResult<TokenType> {
+        let mut s = String::new();
+        loop {
+            match self.advance() {
+                None => {
+                    return Err(self.scan_error(
+                        ScanErrorType::UnterminatedString,
+                        "missing \"\"\" delimiter",
+                    ))
+                }
+                Some('"') if self.peek() == Some('"') && self.peek_next() == Some('"') => {
+                    self.advance();
+                    self.advance();
+                    return Ok(TokenType::Str(s));
+                }
+                Some('\\') => s.push(self.escape()?),
+                Some(c) if self.at_crlf(c) => (),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Decodes the escape sequence following a `\`: `\n`, `\t`, `\r`, `\0`, `\\`, `\"`, `\$`,
+    /// `\xNN` (two hex digits) or `\u{N...}` (a Unicode code point).
+    fn escape(&mut self) -> LoxResult<char> {
+        let Some(c) = self.advance() else {
+            return Err(self.scan_error(ScanErrorType::UnterminatedString, "missing \" delimiter"));
+        };
+        let code = match c {
+            'n' => return Ok('\n'),
+            't' => return Ok('\t'),
+            'r' => return Ok('\r'),
+            '0' => return Ok('\0'),
+            '\\' | '"' | '$' => return Ok(c),
+            'x' => {
+                let mut digits = String::new();
+                for _ in 0..2 {
+                    match self.peek() {
+                        Some(c) if c.is_ascii_hexdigit() => digits.push(c),
+                        _ => {
+                            return Err(self.scan_error(
+                                ScanErrorType::InvalidEscape,
+                                "expected two hex digits after `\\x`",
+                            ))
+                        }
+                    }
+                    self.advance();
+                }
+                u32::from_str_radix(&digits, 16).unwrap()
+            }
+            'u' => {
+                if !self.next_match('{') {
+                    return Err(
+                        self.scan_error(ScanErrorType::InvalidEscape, "expected `{` after `\\u`")
+                    );
+                }
+                let mut digits = String::new();
+                while let Some(c) = self.peek() {
+                    if !c.is_ascii_hexdigit() {
+                        break;
+                    }
+                    digits.push(c);
+                    self.advance();
+                }
+                if digits.is_empty() || digits.len() > 6 || !self.next_match('}') {
+                    return Err(self.scan_error(
+                        ScanErrorType::InvalidEscape,
+                        "expected 1 to 6 hex digits between braces after `\\u`",
+                    ));
+                }
+                u32::from_str_radix(&digits, 16).unwrap()
+            }
+            _ => {
+                return Err(self.scan_error(ScanErrorType::InvalidEscape, "unknown escape sequence"))
+            }
+        };
+        char::from_u32(code).ok_or_else(|| {
+            self.scan_error(ScanErrorType::InvalidEscape, "invalid Unicode code point")
+        })
+    }
+
     fn number(&mut self) -> LoxResult<TokenType> {
         while let Some(c) = self.peek() {
             if !c.is_ascii_digit() {
@@ -292,6 +497,9 @@ impl<'a> Scanner<'a> {
         let num = self.source[self.start..self.current]
             .parse::<f64>()
             .unwrap();
+        if !num.is_finite() {
+            return Err(self.scan_error(ScanErrorType::InvalidNumber, "number too large"));
+        }
         Ok(TokenType::Number(num))
     }
 
@@ -306,7 +514,7 @@ impl<'a> Scanner<'a> {
         let r#type = KEYWORDS
             .get(s)
             .cloned()
-            .unwrap_or(TokenType::Identifier(s.to_owned()));
+            .unwrap_or_else(|| TokenType::Identifier(Symbol::intern(s)));
         Ok(r#type)
     }
 
@@ -320,6 +528,18 @@ impl<'a> Scanner<'a> {
             current: 0,
             line: 1,
             col: 1,
+            start_line: 1,
+            start_col: 1,
+            interpolations: vec![],
+            keep_comments: false,
+        }
+    }
+
+    /// Scanner emitting comments as `Comment` tokens instead of discarding them.
+    pub fn with_comments(source: &'a str) -> Self {
+        Self {
+            keep_comments: true,
+            ..Self::new(source)
         }
     }
 
@@ -0,0 +1,779 @@
+use crate::helpers::{
+    assert_failure, assert_failure_and_check_stderr, assert_success_and_check_stdout, run_with_args,
+};
+use assert_cmd::Command;
+use rlox::errors::LoxError;
+use rlox::interpreter::{Environment, Interpreter, Options};
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use std::time::Duration;
+
+#[test]
+fn group_digits_large_integer() {
+    let source = r#"
+println(group_digits(1234567));
+println(group_digits(1234567.25));
+println(group_digits(999));
+"#;
+    let output = "
+1,234,567
+1,234,567.25
+999
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn group_digits_negative_number() {
+    assert_success_and_check_stdout("println(group_digits(-98765432));", "-98,765,432");
+}
+
+#[test]
+fn numbers_display_without_trailing_decimal() {
+    let source = r#"
+println(10 / 4);
+println(2.0);
+println(100000000000000000000);
+"#;
+    let output = "
+2.5
+2
+100000000000000000000
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn repr_keeps_float_form() {
+    let source = r#"
+println(repr(10 / 4));
+println(repr(2.0));
+println(repr(100000000000000000000));
+println(repr("abc"));
+"#;
+    let output = r#"
+2.5
+2.0
+1e20
+"abc"
+"#;
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn assert_passes_on_truthy_value() {
+    assert_success_and_check_stdout("assert(1 == 1); println(\"done\");", "done");
+}
+
+#[test]
+fn assert_fails_with_message() {
+    assert_failure_and_check_stderr(r#"assert(false, "boom");"#, "assertion failed: boom");
+    assert_failure_and_check_stderr("assert(nil);", "assertion failed");
+}
+
+#[test]
+fn bytes_round_trip_through_file() {
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("rlox-bytes-in-{}", std::process::id()));
+    let output = dir.join(format!("rlox-bytes-out-{}", std::process::id()));
+    std::fs::write(&input, [0x00, 0x7f, b'a', 0xff]).unwrap();
+    let source = format!(
+        r#"
+let bytes = read_bytes("{}");
+println(len(bytes));
+println(bytes[3]);
+println(bytes);
+write_bytes("{}", bytes);
+"#,
+        input.display(),
+        output.display()
+    );
+    let expected = r#"
+4
+255
+b"\x00\x7fa\xff"
+"#;
+    assert_success_and_check_stdout(&source, expected);
+    assert_eq!(std::fs::read(&output).unwrap(), [0x00, 0x7f, b'a', 0xff]);
+    std::fs::remove_file(input).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
+#[test]
+fn string_case_and_whitespace_helpers() {
+    let source = r#"
+println(upper("abc") == "ABC");
+println(lower("AbC") == "abc");
+println(trim("  x  ") == "x");
+"#;
+    let output = "
+true
+true
+true
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn string_helpers_reject_non_strings() {
+    assert_failure_and_check_stderr("upper(3);", "upper: expected a string");
+}
+
+#[test]
+fn is_integer_detects_whole_numbers() {
+    let source = r#"
+println(is_integer(3.0));
+println(is_integer(3.5));
+println(is_integer("3"));
+"#;
+    let output = "
+true
+false
+false
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn arrays() {
+    let source = r#"
+let a = [1, "two", [3]];
+println(a);
+println(len(a));
+println(a[1]);
+println(a[2][0]);
+"#;
+    let output = r#"
+[1, "two", [3]]
+3
+two
+3
+"#;
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr("[1, 2][2];", "index `2` out of range for length 2");
+}
+
+#[test]
+fn split_and_join() {
+    let source = r#"
+println(join(split("a,b", ","), ";") == "a;b");
+println(split("a,b,c", ","));
+println(join([1, 2, 3], "-"));
+"#;
+    let output = r#"
+true
+["a", "b", "c"]
+1-2-3
+"#;
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr(r#"join("abc", ",");"#, "join: expected an array");
+}
+
+#[test]
+fn range_builds_arrays() {
+    let source = r#"
+println(range(3));
+println(range(1, 4));
+println(range(0, 10, 2));
+println(range(3, 0, -1));
+"#;
+    let output = "
+[0, 1, 2]
+[1, 2, 3]
+[0, 2, 4, 6, 8]
+[3, 2, 1]
+";
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr("range(0, 10, 0);", "range: step cannot be zero");
+}
+
+#[test]
+fn range_rejects_unbounded_ranges() {
+    assert_failure_and_check_stderr(
+        "let s = 10000000000000000; range(s, s + 4, 1);",
+        "range: step 1 is too small to change 10000000000000000",
+    );
+    assert_failure_and_check_stderr(
+        "range(0, 1000000000000);",
+        "range: range of 1000000000000 elements exceeds the maximum",
+    );
+}
+
+#[test]
+fn randint_and_round_check_their_arguments() {
+    let source = r#"
+println(randint(3, 3));
+println(randint(-1, -1));
+println(round(3.14159, 2));
+println(round(2.5, 0));
+"#;
+    assert_success_and_check_stdout(source, "3\n-1\n3.14\n3");
+    let errors = [
+        (
+            "randint(5, 1);",
+            "randint: low bound 5 is greater than high bound 1",
+        ),
+        (
+            "randint(0, 100000000000000000000);",
+            "randint: expected two integers arguments",
+        ),
+        (
+            "round(1, 400);",
+            "round: precision must be between 0 and 22 but got 400",
+        ),
+        (
+            "round(1, -1);",
+            "round: precision must be between 0 and 22 but got -1",
+        ),
+        ("round(1, 2.5);", "round: precision must be an integer"),
+    ];
+    for (source, error) in errors {
+        assert_failure_and_check_stderr(source, error);
+    }
+}
+
+#[test]
+fn string_methods() {
+    let source = r#"
+println("hello".len());
+println("Hi".lower());
+println(" a,b ".trim().upper().split(","));
+"#;
+    let output = r#"
+5
+hi
+["A", "B"]
+"#;
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr(r#""abc".reverse();"#, "string has no method `reverse`");
+}
+
+#[test]
+fn maps() {
+    let source = r#"
+let m = { "b": 2, "a": 1 };
+println(m["a"]);
+m["c"] = [3];
+println(m);
+println(len(m));
+for (key in keys(m))
+    println(key);
+println(values(m));
+"#;
+    let output = r#"
+1
+{"a": 1, "b": 2, "c": [3]}
+3
+a
+b
+c
+[1, 2, [3]]
+"#;
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr(r#"let m = { 1: "a" };"#, "expected a string as key");
+    assert_failure_and_check_stderr(r#"println({ "a": 1 }["b"]);"#, r#"key "b" not found"#);
+}
+
+#[test]
+fn array_item_assignment() {
+    let source = r#"
+let a = [1, 2, 3];
+a[1] = "two";
+println(a);
+"#;
+    assert_success_and_check_stdout(source, r#"[1, "two", 3]"#);
+}
+
+#[test]
+fn failed_assert_reports_line() {
+    let source = r#"
+let x = 1;
+assert(x == 1);
+assert(x == 2, "x should be 2");
+"#;
+    assert_failure_and_check_stderr(source, "line 4: assertion failed: x should be 2");
+}
+
+#[test]
+fn contains_and_index_of() {
+    let source = r#"
+println(contains("hello", "ell"));
+println(contains("hello", "xyz"));
+println(index_of([1, 2, 3], 2) == 1);
+println(index_of([1, 2, 3], 4));
+println(index_of("hello", "llo"));
+println("hello".contains("lo"));
+"#;
+    let output = "
+true
+false
+true
+-1
+2
+true
+";
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr("contains(1, 2);", "contains: expected a string");
+}
+
+#[test]
+fn files_round_trip() {
+    let path = std::env::temp_dir().join(format!("rlox-file-{}", std::process::id()));
+    let source = format!(
+        r#"
+write_file("{0}", "first line,second line");
+let content = read_file("{0}");
+println(split(content, ",")[1]);
+"#,
+        path.display()
+    );
+    assert_success_and_check_stdout(&source, "second line");
+    std::fs::remove_file(&path).unwrap();
+    assert_failure_and_check_stderr(
+        &format!(r#"read_file("{}");"#, path.display()),
+        "cannot read",
+    );
+}
+
+#[test]
+fn between() {
+    let source = r#"
+println(between(5, 1, 10));
+println(between(0, 1, 10));
+println(between("b", "a", "c"));
+"#;
+    assert_success_and_check_stdout(source, "true\nfalse\ntrue");
+    assert_failure_and_check_stderr(
+        "between(5, 1, \"z\");",
+        "between: expected three numbers or three strings",
+    );
+}
+
+#[test]
+fn get_or() {
+    let source = r#"
+class Point {}
+let p = Point();
+p.x = 1;
+println(get_or(p, "x", 0));
+println(get_or(p, "y", 0));
+"#;
+    assert_success_and_check_stdout(source, "1\n0");
+    let source = r#"
+class Circle {
+    fn area { println("computing"); return 3 * this.r * this.r; }
+    fn broken { return this.missing; }
+}
+let c = Circle();
+c.r = 2;
+println(get_or(c, "area", 0));
+println(get_or(c, "broken", 0));
+"#;
+    assert_failure(source)
+        .code(70)
+        .stdout("computing\n12\n")
+        .stderr(predicates::str::contains("undefined property `missing`"));
+    assert_failure_and_check_stderr(
+        r#"get_or(3, "x", 0);"#,
+        "get_or: expected an instance and a field name",
+    );
+}
+
+#[test]
+fn type_of() {
+    let source = r#"
+class Point {}
+fn f() {}
+println(typeof(3) == "number");
+let values = ["a", true, nil, f, Point, Point(), [1], {"k": 1}];
+for (value in values) println(typeof(value));
+"#;
+    let output = "
+true
+string
+bool
+nil
+function
+class
+instance
+array
+map
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn builtin_purity() {
+    use rlox::functions::{Callable, Clock, Exit, Format, Len, Print, Rand, Round, Upper};
+
+    let pure: [&dyn Callable; 3] = [&Round, &Len, &Upper];
+    // `format` calls the `to_string` method of instances
+    let impure: [&dyn Callable; 5] = [&Print, &Rand, &Clock, &Exit, &Format];
+    for callable in pure {
+        assert!(callable.is_pure(), "{} should be pure", callable.name());
+    }
+    for callable in impure {
+        assert!(
+            !callable.is_pure(),
+            "{} should not be pure",
+            callable.name()
+        );
+    }
+}
+
+#[test]
+fn deep_eq() {
+    let source = r#"
+class Point {}
+fn point(x, y) {
+    let p = Point();
+    p.x = x;
+    p.y = y;
+    return p;
+}
+let a = [1, {"k": [2, 3]}, point(1, 2)];
+let b = [1, {"k": [2, 3]}, point(1, 2)];
+println(deep_eq(a, b));
+println(a == b);
+println(deep_eq(a, [1, {"k": [2, 4]}, point(1, 2)]));
+println(deep_eq(point(1, 2), point(1, 3)));
+let c = [1];
+c[0] = c;
+let d = [1];
+d[0] = d;
+println(deep_eq(c, d));
+"#;
+    let output = "
+true
+false
+false
+false
+true
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn sleep() {
+    let source = r#"
+let start = clock();
+sleep(10);
+println(clock() - start >= 0.01);
+"#;
+    assert_success_and_check_stdout(source, "true");
+    assert_failure_and_check_stderr(
+        "sleep(-1);",
+        "sleep: expected a non-negative integer number of milliseconds",
+    );
+    assert_failure_and_check_stderr(
+        "sleep(1.5);",
+        "sleep: expected a non-negative integer number of milliseconds",
+    );
+}
+
+#[test]
+fn num_cmp() {
+    let source = r#"
+println(num_cmp("3", "10"));
+println(num_cmp("5", "5"));
+println(num_cmp("10", 3));
+println("3" < "10");
+"#;
+    assert_success_and_check_stdout(source, "-1\n0\n1\nfalse");
+    assert_failure_and_check_stderr(
+        r#"num_cmp("three", "10");"#,
+        "num_cmp: expected numbers or numeric strings",
+    );
+}
+
+#[test]
+fn print_does_not_add_a_newline() {
+    let source = r#"
+print("a");
+print("b");
+println("c");
+println("d");
+print(1);
+"#;
+    run_with_args(&[], source).success().stdout("abc\nd\n1");
+}
+
+#[test]
+fn format_substitutes_placeholders() {
+    let source = r#"
+println(format("{} + {} = {}", 1, 2, 3));
+println(format("no placeholders"));
+println(format("{}{}", "a", [true, nil]));
+"#;
+    let output = "
+1 + 2 = 3
+no placeholders
+a[true, nil]
+";
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr(
+        r#"format("{} and {}", 1);"#,
+        "format: expected 2 arguments for the placeholders but got 1",
+    );
+    assert_failure_and_check_stderr("format();", "expected at least 1 argument but got 0");
+}
+
+#[test]
+fn time_it_returns_result_and_elapsed_time() {
+    let source = r#"
+fn work() {
+    let total = 0;
+    for (let i = 0; i < 100; i = i + 1) total = total + i;
+    return total;
+}
+let timed = time_it(work);
+println(len(timed));
+println(timed[0]);
+println(timed[1] >= 0);
+"#;
+    assert_success_and_check_stdout(source, "2\n4950\ntrue");
+    assert_failure_and_check_stderr(
+        "fn f(x) {} time_it(f);",
+        "time_it: expected a function taking no arguments",
+    );
+}
+
+#[test]
+fn ord_and_chr_round_trip() {
+    let source = r#"
+println(ord("A"));
+println(chr(65));
+println(ord(chr(233)));
+println(ord(chr(128512)));
+"#;
+    assert_success_and_check_stdout(source, "65\nA\n233\n128512");
+    let errors = [
+        (r#"ord("ab");"#, "ord: expected a single-character string"),
+        (r#"ord("");"#, "ord: expected a single-character string"),
+        ("chr(65.5);", "chr: expected an integer but got 65.5"),
+        ("chr(55296);", "chr: 55296 is not a valid code point"),
+        ("chr(-1);", "chr: -1 is not a valid code point"),
+    ];
+    for (source, error) in errors {
+        assert_failure_and_check_stderr(source, error);
+    }
+}
+
+#[test]
+fn dir_lists_instance_fields() {
+    let source = r#"
+class Point {}
+let point = Point();
+point.y = 2;
+point.x = 1;
+dir(point);
+"#;
+    run_with_args(&[], source).success().stdout("x\ny\n");
+    assert_failure_and_check_stderr("dir(1);", "dir: expected an instance but got number");
+}
+
+#[test]
+fn map_filter_and_reduce_arrays() {
+    let source = r#"
+fn double(x) {
+    return 2 * x;
+}
+fn is_even(x) {
+    return is_integer(x / 2);
+}
+fn add(a, b) {
+    return a + b;
+}
+let numbers = [1, 2, 3, 4, 5];
+println(map(numbers, double));
+println(filter(numbers, is_even));
+println(reduce(numbers, add, 0));
+println(reduce([], add, "empty"));
+"#;
+    let output = "
+[2, 4, 6, 8, 10]
+[2, 4]
+15
+empty
+";
+    assert_success_and_check_stdout(source, output);
+    let errors = [
+        ("map(1, print);", "map: expected an array and a function"),
+        (
+            "fn add(a, b) { return a + b; } map([1], add);",
+            "map: `add` cannot be called with 1 arguments",
+        ),
+        (
+            r#"fn fail(x) { return x + nil; } filter([1], fail);"#,
+            "unsupported operation",
+        ),
+    ];
+    for (source, error) in errors {
+        assert_failure_and_check_stderr(source, error);
+    }
+}
+
+#[test]
+fn getenv_reads_environment_variables() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("RLOX_GREETING", "hello")
+        .env_remove("RLOX_UNSET")
+        .args([
+            "-c",
+            r#"println(getenv("RLOX_GREETING")); println(getenv("RLOX_UNSET"));"#,
+        ])
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stdout("hello\nnil\n");
+}
+
+#[test]
+fn number_formatters() {
+    let source = r#"
+println(hex(255));
+println(hex(-16));
+println(bin(10));
+println(bin(0));
+println(sci(12345));
+println(sci(0.00042));
+"#;
+    assert_success_and_check_stdout(source, "0xff\n-0x10\n0b1010\n0b0\n1.2345e4\n4.2e-4");
+    let errors = [
+        ("hex(1.5);", "hex: expected an integer but got 1.5"),
+        (
+            "hex(9223372036854775808);",
+            "hex: expected an integer but got 9223372036854776000",
+        ),
+        ("bin(\"10\");", "bin: expected a number"),
+        ("sci(nil);", "sci: expected a number"),
+    ];
+    for (source, error) in errors {
+        assert_failure_and_check_stderr(source, error);
+    }
+}
+
+#[test]
+fn abs_min_max_edge_cases() {
+    let source = r#"
+println(abs(-0));
+println(abs(-2.5));
+println(min(0, -0));
+println(min(-0, 0));
+println(max(-0, 0));
+println(max(0, -0));
+println(min(3, 1, 2));
+println(max(3));
+"#;
+    assert_success_and_check_stdout(source, "0\n2.5\n-0\n-0\n0\n0\n1\n3");
+    assert_failure_and_check_stderr("max(1, nil);", "max: expected numbers");
+    assert_failure_and_check_stderr("min();", "expected at least 1 argument but got 0");
+}
+
+#[test]
+fn arithmetic_never_produces_infinities() {
+    let big = format!("1{}", "0".repeat(200));
+    assert_failure_and_check_stderr(
+        &format!("println({big} * {big});"),
+        "`*` overflowed: the result is not a finite number",
+    );
+    assert_failure_and_check_stderr(
+        &format!("println(-{big} * {big} - {big} * {big});"),
+        "overflowed: the result is not a finite number",
+    );
+    assert_failure_and_check_stderr("println(1 / 0);", "division by zero");
+}
+
+#[test]
+fn clamp_and_sign() {
+    let source = r#"
+println(clamp(-5, 0, 10));
+println(clamp(4.5, 0, 10));
+println(clamp(15, 0, 10));
+println(clamp(3, 3, 3));
+println(sign(-2.5));
+println(sign(0));
+println(sign(-0));
+println(sign(7));
+"#;
+    assert_success_and_check_stdout(source, "0\n4.5\n10\n3\n-1\n0\n0\n1");
+    let errors = [
+        (
+            "clamp(1, 10, 0);",
+            "clamp: lower bound 10 is greater than upper bound 0",
+        ),
+        ("clamp(1, \"0\", 2);", "clamp: expected numbers"),
+        ("sign(nil);", "sign: expected a number"),
+    ];
+    for (source, error) in errors {
+        assert_failure_and_check_stderr(source, error);
+    }
+}
+
+#[test]
+fn assert_eq_shows_both_values() {
+    assert_success_and_check_stdout(
+        r#"assert_eq(1 + 2, 3); assert_eq([1, "a"], [1, "a"]); println("done");"#,
+        "done",
+    );
+    assert_failure_and_check_stderr("assert_eq(3, 4);", "assertion failed: 3 != 4");
+    assert_failure_and_check_stderr(r#"assert_eq("1", 1);"#, r#"assertion failed: "1" != 1"#);
+}
+
+#[test]
+fn bare_environment_has_no_builtins() {
+    let run = |env: &mut Environment, source: &str| {
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().unwrap();
+        let statements = Parser::new(scanner.tokens).parse().unwrap();
+        Interpreter::new().interpret(env, &statements)
+    };
+    let mut env = Environment::bare();
+    let error = run(&mut env, "clock();").err().expect("expected an error");
+    assert!(error.to_string().contains("name `clock` is not defined"));
+
+    env.add_builtin("clock").unwrap();
+    assert!(run(&mut env, "clock();").is_ok());
+    assert!(run(&mut env, "exit(1);").is_err());
+    assert!(env.add_builtin("missing").is_err());
+}
+
+#[test]
+fn exit_is_returned_to_the_host() {
+    let mut scanner = Scanner::new("println(1); exit(3); println(2);");
+    scanner.scan_tokens().unwrap();
+    let statements = Parser::new(scanner.tokens).parse().unwrap();
+    let result = Interpreter::new().interpret(&mut Environment::new(), &statements);
+    assert!(matches!(result, Err(LoxError::Exit(3))));
+}
+
+#[test]
+fn entry_points_return_the_exit_code() {
+    assert_eq!(rlox::run_source("exit(3);", Options::default()), Err(3));
+    assert_eq!(rlox::run_source("exit(0);", Options::default()), Err(0));
+    assert_eq!(rlox::run_expression("quit()", Options::default()), Err(0));
+    assert_eq!(rlox::run_source("let x = 1;", Options::default()), Ok(()));
+    assert_eq!(rlox::run_source("nil();", Options::default()), Err(70));
+}
+
+#[test]
+fn self_containing_containers() {
+    let source = r#"
+let a = [1];
+a[0] = a;
+println(a == a);
+println(a);
+println(repr(a));
+let m = {"k": 1};
+m["k"] = m;
+println(m == m);
+println(m);
+let b = [2];
+println([b, b]);
+"#;
+    assert_success_and_check_stdout(
+        source,
+        "true\n[[...]]\n[[...]]\ntrue\n{\"k\": {...}}\n[[2], [2]]",
+    );
+}
@@ -1,15 +1,20 @@
 use crate::helpers::{
-    assert_failure, assert_success_and_check_stderr, assert_success_and_check_stdout,
+    assert_failure, assert_failure_and_check_stderr, assert_success,
+    assert_success_and_check_stdout, run_with_args,
 };
+use predicates::boolean::PredicateBooleanExt;
+use rlox::interpreter::{Environment, Interpreter};
+use rlox::parser::{Parser, Stmt};
+use rlox::scanner::{Scanner, TokenType};
 
 #[test]
 fn if_statement() {
     let source = r#"
 let x = 3;
 if (x > 9) {
-    print("x > 9!");
+    println("x > 9!");
 } else {
-    print("x <= 9!");
+    println("x <= 9!");
 }
 "#;
     let output = "x <= 9!
@@ -23,7 +28,7 @@ fn for_statement() {
 let i = 0;
 for (; i < 10; i = i + 1)
     if (i > 5)
-        print(i);
+        println(i);
 "#;
     let output = "
 6
@@ -38,7 +43,7 @@ for (; i < 10; i = i + 1)
 fn for_constant_expr() {
     let source = r#"
 for (let i = 0; i < 10; i = i + 1)
-    print("Alphonse");
+    println("Alphonse");
 "#;
     let output = "Alphonse\n".repeat(10);
     assert_success_and_check_stdout(source, &output);
@@ -51,7 +56,7 @@ let i = 3;
 while (i < 8) {
     i = i + 1;
     if (i > 4)
-        print(i);
+        println(i);
 }
 "#;
     let output = "
@@ -69,7 +74,7 @@ fn continue_statement() {
 for (let i = 0; i < 20; i = i + 1) {
     if (i <= 10)
         continue;
-    print(i);
+    println(i);
 }
 "#;
     let output = "
@@ -95,7 +100,7 @@ while (i < 10) {
     if (i >= 7)
         break;
 }
-print(i);
+println(i);
 "#;
     assert_success_and_check_stdout(source, "8");
 }
@@ -108,7 +113,7 @@ for (let i = 0; i < 10; i = i + 1) {
 }
 break;
 "#;
-    assert_success_and_check_stderr(source, "`break` outside loop");
+    assert_failure_and_check_stderr(source, "`break` outside loop");
 }
 
 #[test]
@@ -120,7 +125,7 @@ for (let i = 0; i < 10; i = i + 1) {
 }
 continue;
 "#;
-    assert_success_and_check_stderr(source, "`continue` outside loop");
+    assert_failure_and_check_stderr(source, "`continue` outside loop");
 }
 
 #[test]
@@ -138,7 +143,7 @@ for (;;) {
     }
     break;
 }
-print("Outside!");
+println("Outside!");
 "#;
     assert_success_and_check_stdout(source, "Outside!");
 }
@@ -148,7 +153,7 @@ fn infinite_loops() {
     let source = r#"
 for (;;)         // infinite loop
     ;
-print("Outside!");
+println("Outside!");
 "#;
     assert_failure(source);
 
@@ -171,7 +176,218 @@ for (let i = 0; i < n - 1; i = i + 1) {
     b = b + a;
     a = tmp;
 }
-print(a);    // the n-th Fibonacci number
+println(a);    // the n-th Fibonacci number
 "#;
     assert_success_and_check_stdout(source, "34");
 }
+
+#[test]
+fn runtime_error_stops_execution() {
+    let source = r#"
+println("before");
+println(-"a");
+println("after");
+"#;
+    assert_failure(source)
+        .code(70)
+        .stderr(predicates::str::contains(
+            "unary operator `-` only works with numbers",
+        ))
+        .stdout(predicates::str::contains("after").not());
+}
+
+#[test]
+fn for_in_statement() {
+    let source = r#"
+let total = 0;
+for (x in range(0, 10)) {
+    if (x == 2) continue;
+    if (x == 5) break;
+    total = total + x;
+}
+println(total);
+for (c in "ab")
+    println(c);
+"#;
+    let output = "
+8
+a
+b
+";
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr("for (x in 3) println(x);", "is not iterable");
+}
+
+#[test]
+fn labeled_loops() {
+    let source = r#"
+let found = nil;
+outer: for (let i = 0; i < 5; i = i + 1) {
+    let j = 0;
+    while (true) {
+        if (j == 3) continue outer;
+        if (i * j == 6) {
+            found = [i, j];
+            break outer;
+        }
+        j = j + 1;
+    }
+}
+println(found);
+rows: for (row in [[1, 2], [3, 4]]) {
+    for (x in row) {
+        if (x == 2) continue rows;
+        println(x);
+    }
+}
+"#;
+    let output = "
+[3, 2]
+1
+3
+4
+";
+    assert_success_and_check_stdout(source, output);
+    let source = "outer: while (true) { while (true) { break inner; } }";
+    assert_failure_and_check_stderr(source, "no enclosing loop labeled `inner`");
+}
+
+#[test]
+fn switch_statement() {
+    let source = r#"
+fn describe(x) {
+    switch (x) {
+        case 1:
+            return "one";
+        case "two":
+            return "two";
+        default:
+            return "other";
+    }
+}
+println(describe(1));
+println(describe("two"));
+println(describe(nil));
+switch (2) {
+    case 1: println("first");
+    case 2: println("second");
+    case 2: println("third");
+}
+"#;
+    let output = "
+one
+two
+other
+second
+";
+    assert_success_and_check_stdout(source, output);
+    let source = "switch (1) { default: println(1); default: println(2); }";
+    assert_failure_and_check_stderr(source, "duplicate `default` in switch");
+}
+
+#[test]
+fn do_while_statement() {
+    let source = r#"
+let runs = 0;
+do {
+    runs = runs + 1;
+} while (false);
+println(runs);
+let i = 0;
+do {
+    i = i + 1;
+    if (i == 2) continue;
+    if (i == 4) break;
+    println(i);
+} while (i < 10);
+"#;
+    let output = "
+1
+1
+3
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn loop_control_does_not_cross_function_boundaries() {
+    assert_failure_and_check_stderr("while (true) { fn f() { break; } }", "`break` outside loop");
+    assert_failure_and_check_stderr(
+        "for (x in [1]) { fn f() { continue; } }",
+        "`continue` outside loop",
+    );
+    assert_failure_and_check_stderr(
+        "outer: while (true) { fn f() { while (true) { break outer; } } }",
+        "no enclosing loop labeled `outer`",
+    );
+    let source = r#"
+while (true) {
+    fn f() {
+        for (x in [1, 2]) { if (x == 2) break; println(x); }
+    }
+    f();
+    break;
+}
+let i = 0;
+while (i < 1) i = i + 1;
+fn g() { return "after loop"; }
+println(g());
+"#;
+    assert_success_and_check_stdout(source, "1\nafter loop");
+}
+
+#[test]
+fn stray_break_is_a_runtime_error() {
+    let mut env = Environment::new();
+    for statement in [Stmt::Break(None), Stmt::Continue(Some("outer".to_string()))] {
+        let result = Interpreter::new().interpret(&mut env, &[statement]);
+        let error = result.err().expect("expected an error");
+        assert!(error.to_string().contains("outside loop"));
+    }
+}
+
+#[test]
+fn else_if_chain_is_a_single_if() {
+    let source = r#"
+fn describe(n) {
+    if (n < 0) return "negative";
+    else if (n == 0) return "zero";
+    else return "positive";
+}
+println(describe(-3));
+println(describe(0));
+println(describe(8));
+"#;
+    assert_success_and_check_stdout(source, "negative\nzero\npositive");
+    let output = "\
+If (< x 0)
+  Expr (= x 0)
+Else If (== x 0)
+  Expr (= x 1)
+Else
+  Expr (= x 2)
+";
+    let source = "if (x < 0) x = 0; else if (x == 0) x = 1; else x = 2;";
+    run_with_args(&["--ast"], source).success().stdout(output);
+}
+
+#[test]
+fn empty_programs_do_nothing() {
+    for source in ["", "  \n\t\n", "// only a comment\n// and another"] {
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().unwrap();
+        let types = scanner
+            .tokens
+            .iter()
+            .map(|t| t.r#type.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(types, [TokenType::Eof]);
+        assert_eq!(Parser::new(scanner.tokens).parse().unwrap(), []);
+        assert_success(source).stdout("").stderr("");
+    }
+
+    let mut scanner = Scanner::new(";");
+    scanner.scan_tokens().unwrap();
+    assert_eq!(Parser::new(scanner.tokens).parse().unwrap(), [Stmt::Null]);
+    assert_success(";").stdout("").stderr("");
+}
@@ -0,0 +1,145 @@
+use crate::helpers::{
+    assert_failure, assert_failure_and_check_stderr, assert_success_and_check_stdout,
+};
+use rlox::errors::{LoxError, ParseError, RuntimeError, ScanError, ScanErrorType};
+use rlox::parser::Parser;
+use rlox::scanner::{Scanner, Token, TokenType};
+
+#[test]
+fn error_messages_have_balanced_parentheses() {
+    let token = Token {
+        r#type: TokenType::Semicolon,
+        lexeme: ";".to_string(),
+        line: 1,
+        col: 4,
+    };
+    let errors = [
+        ScanError::build(
+            1,
+            2,
+            "missing \" delimiter".to_string(),
+            ScanErrorType::UnterminatedString,
+        ),
+        ParseError::build(token, "expected expression".to_string()),
+        RuntimeError::build("division by zero".to_string()),
+        LoxError::Internal("could not get system time".to_string()),
+    ];
+    for error in errors {
+        let message = error.to_string();
+        let opening = message.matches('(').count();
+        let closing = message.matches(')').count();
+        assert_eq!(opening, closing, "unbalanced parentheses in `{message}`");
+    }
+}
+
+#[test]
+fn invalid_assignment_targets_are_named() {
+    let cases = [
+        ("let x = 1; 3 = x;", "cannot assign to a literal"),
+        (
+            "fn f() {} let x = 1; f() = x;",
+            "cannot assign to a function call",
+        ),
+        (
+            "let a = 1; let b = 2; a + b = 3;",
+            "cannot assign to an operation",
+        ),
+        (
+            "let a = 1; (a) = 3;",
+            "cannot assign to a parenthesized expression",
+        ),
+    ];
+    for (source, message) in cases {
+        assert_failure_and_check_stderr(source, message);
+    }
+}
+
+#[test]
+fn missing_argument_or_parameter_before_comma() {
+    let cases = [
+        ("fn f(a) {} f(,1);", "expected argument before `,`"),
+        ("fn f(a, b) {} f(1,,2);", "expected argument before `,`"),
+        ("fn g(,a) {}", "expected parameter before `,`"),
+    ];
+    for (source, message) in cases {
+        assert_failure_and_check_stderr(source, message);
+    }
+}
+
+#[test]
+fn deeply_nested_expression_reports_error() {
+    let depth = 5000;
+    let source = format!("{}1{};", "(".repeat(depth), ")".repeat(depth));
+    assert_failure_and_check_stderr(&source, "expression nesting too deep");
+    let source = format!("{}1;", "-".repeat(depth));
+    assert_failure_and_check_stderr(&source, "expression nesting too deep");
+
+    let nested = |depth| {
+        let source = format!("{}1{};", "(".repeat(depth), ")".repeat(depth));
+        let mut scanner = Scanner::new(&source);
+        scanner.scan_tokens().unwrap();
+        Parser::with_max_depth(scanner.tokens, 4).parse()
+    };
+    // the statement and the expression it holds are two levels already
+    assert!(nested(2).is_ok());
+    assert!(nested(3).is_err());
+}
+
+#[test]
+fn long_operator_chains_are_bounded() {
+    let chain = |terms| format!("let x = 1; print({});", vec!["x"; terms].join(" + "));
+    assert_success_and_check_stdout(&chain(400), "400");
+    assert_failure_and_check_stderr(&chain(5000), "expression nesting too deep");
+}
+
+#[test]
+fn deeply_nested_statements_report_error() {
+    let depth = 5000;
+    let source = format!("{}{}", "{".repeat(depth), "}".repeat(depth));
+    assert_failure_and_check_stderr(&source, "statement nesting too deep");
+    let source = format!("{}print(1);", "if (true) ".repeat(depth));
+    assert_failure_and_check_stderr(&source, "nesting too deep");
+}
+
+#[test]
+fn division_by_zero_is_reported_at_its_line() {
+    let cases = [
+        (
+            "let x = 1;\nprintln(x / 0);",
+            "line 2: division by zero in `/`",
+        ),
+        (
+            "let x = 1;\n\nprintln(x % 0);",
+            "line 3: division by zero in `%`",
+        ),
+        ("println(7 % -0);", "line 1: division by zero in `%`"),
+    ];
+    for (source, message) in cases {
+        assert_failure_and_check_stderr(source, message);
+    }
+}
+
+#[test]
+fn missing_semicolon_is_reported_after_the_previous_token() {
+    let cases = [
+        (
+            "let x = 1\nprintln(x);",
+            "line 1: expected `;` after variable declaration (after token `1`)",
+        ),
+        (
+            "let x = 1;\nprintln(x)\n\nx = 2;",
+            "line 2: expected `;` after expression (after token `)`)",
+        ),
+    ];
+    for (source, message) in cases {
+        assert_failure_and_check_stderr(source, message);
+    }
+}
+
+#[test]
+fn syntax_errors_exit_with_code_65() {
+    assert_failure("let = ;")
+        .code(65)
+        .stderr(predicates::str::contains("expected variable name"));
+    assert_failure("println(1); let = ;").code(65).stdout("");
+}
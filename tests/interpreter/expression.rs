@@ -1,6 +1,8 @@
+use crate::helpers::{assert_failure_and_check_stderr, assert_success_and_check_stdout};
 use rlox::expression::Expression::*;
 use rlox::expression::Object::*;
-use rlox::scanner::{Token, TokenType};
+use rlox::parser::{Parser, Stmt};
+use rlox::scanner::{Scanner, Token, TokenType};
 
 #[test]
 fn check_expr_repr() {
@@ -43,4 +45,379 @@ fn check_expr_repr() {
         right: Box::new(expr2),
     };
     assert_eq!(expr.to_string(), "(== (* (- 3.14) (group 3.151)) (- 9.89))");
+    assert_eq!(expr.repr(), "(== (* (- 3.14) (group 3.151)) (- 9.89))");
+    assert_eq!(expr.rpn(), "3.14 - 3.151 * 9.89 - ==");
+}
+
+fn parse_expression(source: &str) -> rlox::expression::Expression {
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens().unwrap();
+    let mut statements = Parser::new(scanner.tokens).parse().unwrap();
+    match statements.remove(0) {
+        Stmt::Expr(expression) => expression,
+        _ => panic!("expected an expression statement"),
+    }
+}
+
+#[test]
+fn expression_printers() {
+    let cases = [
+        ("a or b and !c;", "(or a (and b (! c)))", "a b c ! and or"),
+        (
+            "f(1, \"x\")(y);",
+            "(call (call f 1 \"x\") y)",
+            "f 1 \"x\" call/2 y call/1",
+        ),
+        ("p.x.y = 2;", "(= (. (. p x) y) 2)", "p .x 2 .y="),
+        (
+            "a[0] = [1, 2];",
+            "(= ([] a 0) (array 1 2))",
+            "a 0 1 2 array/2 []=",
+        ),
+        (
+            "x = ({\"k\": 1})[\"k\"];",
+            "(= x ([] (group (map \"k\" 1)) \"k\"))",
+            "\"k\" 1 map/1 \"k\" [] x =",
+        ),
+    ];
+    for (source, repr, rpn) in cases {
+        let expression = parse_expression(source);
+        assert_eq!(expression.repr(), repr);
+        assert_eq!(expression.rpn(), rpn);
+    }
+}
+
+#[test]
+fn bitwise_operators() {
+    let source = r#"
+println(5 & 3);
+println(5 | 3);
+println(5 ^ 3);
+println(1 << 4);
+println(256 >> 4);
+println(~5);
+println(1 | 2 == 3);
+println(1 << 2 + 1);
+"#;
+    let output = "
+1
+7
+6
+16
+16
+-6
+true
+8
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn bitwise_operators_require_integers() {
+    assert_failure_and_check_stderr(
+        "println(1.5 & 3);",
+        "bitwise operator `&` expects integer operands",
+    );
+    assert_failure_and_check_stderr("println(~0.5);", "bitwise operator `~` expects integer");
+    assert_failure_and_check_stderr(
+        "println(9223372036854775808 | 0);",
+        "bitwise operator `|` expects integer operands",
+    );
+    assert_success_and_check_stdout("println(-9223372036854775808 >> 62);", "-2");
+}
+
+#[test]
+fn logical_not() {
+    let source = r#"
+println(!true);
+println(!nil);
+println(!0);
+"#;
+    let output = "
+false
+true
+false
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn multi_line_strings() {
+    let source = "println(\"\"\"a \"quoted\"\nword\"\"\");\nprint(\"plain\nstring\");";
+    assert_success_and_check_stdout(source, "a \"quoted\"\nword\nplain\nstring");
+}
+
+#[test]
+fn string_interpolation() {
+    let source = r#"
+let x = 5;
+println("x is ${x}");
+println("\${x} is literal");
+let a = 1;
+let b = 2;
+println("sum = ${a + b}, nested = ${"<${x}>"}, map = ${ {"k": a}["k"] }");
+"#;
+    let output = "
+x is 5
+${x} is literal
+sum = 3, nested = <5>, map = 1
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn interpolation_displays_any_value() {
+    let source = r#"println("x${nil}y ${true} ${[1, "a"]} ${1 / 4}");"#;
+    assert_success_and_check_stdout(source, "xnily true [1, \"a\"] 0.25");
+}
+
+#[test]
+fn string_indexing_and_slicing() {
+    let source = r#"
+let s = "hello";
+println(s[0]);
+println(s[4]);
+println(s[1:3]);
+println(s[0:5]);
+println(s[2:2] == "");
+println([1, 2, 3, 4][1:3]);
+"#;
+    let output = "
+h
+o
+el
+hello
+true
+[2, 3]
+";
+    assert_success_and_check_stdout(source, output);
+    let errors = [
+        (r#""abc"[3];"#, "index `3` out of range for length 3"),
+        (r#""abc"[1:4];"#, "slice `1:4` out of range for length 3"),
+        (r#""abc"[2:1];"#, "slice start `2` is after its end `1`"),
+        ("1[0:1];", "cannot be sliced"),
+    ];
+    for (source, error) in errors {
+        assert_failure_and_check_stderr(source, error);
+    }
+}
+
+#[test]
+fn increment_and_decrement_operators() {
+    let source = r#"
+let i = 0;
+i++;
+println(i);
+println(i++);
+println(i);
+println(++i);
+println(i--);
+println(--i);
+let total = 0;
+for (let j = 0; j < 4; j++) total = total + j;
+println(total);
+"#;
+    let output = "
+1
+1
+2
+3
+3
+1
+6
+";
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr("1++;", "invalid `++` target: expected a variable");
+    assert_failure_and_check_stderr("--f();", "invalid `--` target: expected a variable");
+}
+
+#[test]
+fn postfix_operators_give_the_previous_value() {
+    let source = r#"
+let i = 0.1;
+println(i++);
+println(i);
+let n = 9007199254740992;
+println(n++);
+println(n--);
+"#;
+    let output = "
+0.1
+1.1
+9007199254740992
+9007199254740992
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn xor_operator() {
+    let source = r#"
+println(true xor false);
+println(true xor true);
+println(nil xor 0);
+fn loud(value) {
+    println("evaluated");
+    return value;
+}
+println(true xor loud(false));
+println(1 == 1 xor 2 == 3 or false);
+"#;
+    let output = "
+true
+false
+true
+evaluated
+true
+true
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn null_coalescing_operator() {
+    let source = r#"
+println(nil ?? 5);
+println(false ?? 5);
+println(0 ?? 5);
+println(nil ?? nil ?? "last");
+fn loud() {
+    println("evaluated");
+    return 1;
+}
+println(2 ?? loud());
+let ports = [nil, 80];
+println(ports[0] ?? 8080);
+println(ports[1] ?? 8080);
+"#;
+    let output = "
+5
+false
+0
+last
+2
+8080
+80
+";
+    assert_success_and_check_stdout(source, output);
+    assert_success_and_check_stdout("println(nil ?? false or true);", "true");
+}
+
+#[test]
+fn instanceof_checks_the_class_of_instances() {
+    let source = r#"
+class Point {}
+class Circle {}
+let p = Point();
+println(p instanceof Point);
+println(p instanceof Circle);
+println(1 instanceof Point);
+"#;
+    assert_success_and_check_stdout(source, "true\nfalse\nfalse");
+    assert_failure_and_check_stderr(
+        "class Point {} println(Point() instanceof 1);",
+        "right operand of `instanceof` must be a class but got number",
+    );
+}
+
+#[test]
+fn instanceof_tells_apart_classes_of_the_same_name() {
+    let source = r#"
+fn make() {
+    class Point {}
+    return Point;
+}
+let First = make();
+let Second = make();
+let p = First();
+println(p instanceof First);
+println(p instanceof Second);
+"#;
+    assert_success_and_check_stdout(source, "true\nfalse");
+}
+
+#[test]
+fn binary_operator_calls_overloading_method() {
+    let source = r#"
+class Sum {}
+class Vector {
+    fn add(other) {
+        let sum = Sum();
+        sum.x = this.x + other.x;
+        sum.y = this.y + other.y;
+        return sum;
+    }
+}
+let a = Vector();
+a.x = 1;
+a.y = 2;
+let b = Vector();
+b.x = 10;
+b.y = 20;
+let c = a + b;
+println(c instanceof Sum);
+println(c.x);
+println(c.y);
+println(a.x);
+println(b.y);
+"#;
+    assert_success_and_check_stdout(source, "true\n11\n22\n1\n20");
+    assert_failure_and_check_stderr(
+        "class A {} println(A() - A());",
+        "unsupported operation between types",
+    );
+}
+
+#[test]
+fn modulo_operator() {
+    assert_success_and_check_stdout("println(7 % 3);", "1");
+    assert_success_and_check_stdout("println(-7 % 3);", "-1");
+    assert_success_and_check_stdout("println(5.5 % 2);", "1.5");
+    assert_success_and_check_stdout("println(1 + 7 % 4 * 2);", "7");
+}
+
+#[test]
+fn block_expression_gives_its_last_value() {
+    let source = r#"
+let x = { let t = 1; t + 1 };
+println(x);
+let m = {"t": 3};
+println(m["t"]);
+println({ println("first"); });
+fn twice(n) { return { let d = n * 2; d }; }
+println(twice(x));
+"#;
+    assert_success_and_check_stdout(source, "2\n3\nfirst\nnil\n4");
+    let source = "let t = 1; let y = { let t = 2; t }; println(t); println(y);";
+    assert_success_and_check_stdout(source, "1\n2");
+}
+
+#[test]
+fn block_expression_can_declare_the_variable_it_initializes() {
+    let source = r#"
+{ let y = { let y = 2; y }; println(y); }
+fn f() { let y = { let y = 3; y }; return y; }
+println(f());
+"#;
+    assert_success_and_check_stdout(source, "2\n3");
+}
+
+#[test]
+fn jumps_cannot_leave_a_block_expression() {
+    let cases = [
+        ("fn f() { let x = { return 1; }; }", "`return` cannot leave"),
+        (
+            "while (true) { let x = { break; }; }",
+            "`break` cannot leave",
+        ),
+        (
+            "for (;;) { let x = { continue; }; }",
+            "`continue` cannot leave",
+        ),
+    ];
+    for (source, message) in cases {
+        assert_failure_and_check_stderr(source, message);
+    }
+    let source = "let x = { fn g() { return 3; } while (true) { break; } g() }; println(x);";
+    assert_success_and_check_stdout(source, "3");
 }
@@ -1,4 +1,6 @@
-use crate::helpers::{assert_success_and_check_stderr, assert_success_and_check_stdout};
+use crate::helpers::{
+    assert_failure, assert_failure_and_check_stderr, assert_success_and_check_stdout, run_with_args,
+};
 
 #[test]
 fn fibonacci() {
@@ -11,7 +13,7 @@ fn fibonacci(n) {
         b = b + a;
         a = tmp;
     }
-    print(a);
+    println(a);
 }
 for (let n = 1; n <= 10; n = n + 1)
     fibonacci(n);
@@ -39,7 +41,7 @@ fn fibonacci(n) {
     if (n == 2) return 1;
     return fibonacci(n - 1) + fibonacci(n - 2);
 }
-print(fibonacci(8));
+println(fibonacci(8));
 "#;
     assert_success_and_check_stdout(source, "13");
 }
@@ -48,9 +50,294 @@ print(fibonacci(8));
 fn return_outside_of_function_detected_correctly() {
     let source = r#"
 fn foo() {
-    print("Hi!");
+    println("Hi!");
 }
 return;
 "#;
-    assert_success_and_check_stderr(source, "`return` outside function");
+    assert_failure_and_check_stderr(source, "`return` outside function");
+}
+
+#[test]
+fn infix_function() {
+    let source = r#"
+infix fn avg(a, b) {
+    return (a + b) / 2;
+}
+let x = 1 + 3 avg 5 * 2;
+println(x);
+println(x avg 0 < 4);
+"#;
+    let output = "
+7
+true
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn infix_function_requires_two_parameters() {
+    let source = r#"
+infix fn neg(a) {
+    return -a;
+}
+"#;
+    assert_failure_and_check_stderr(source, "infix functions take exactly 2 parameters");
+}
+
+#[test]
+fn limit_calls_errors_after_n_calls() {
+    let source = r#"
+fn double(x) {
+    return 2 * x;
+}
+let limited = limit_calls(double, 2);
+println(limited(1));
+println(limited(2));
+println(limited(3));
+"#;
+    assert_failure(source)
+        .stdout(predicates::str::contains("2\n4\n"))
+        .stderr(predicates::str::contains(
+            "`double` was called more than 2 times",
+        ));
+}
+
+#[test]
+fn limit_calls_bounds_recursion() {
+    let source = r#"
+fn f(n) { if (n == 0) return 0; return 1 + f(n - 1); }
+println(limit_calls(f, 5)(3));
+fn t(n) { if (n == 0) return 0; return t(n - 1); }
+println(limit_calls(t, 5)(100));
+"#;
+    assert_failure(source)
+        .stdout(predicates::str::contains("3\n"))
+        .stderr(predicates::str::contains(
+            "`t` was called more than 5 times",
+        ));
+    let source = r#"
+fn f(n) { if (n == 0) return 0; return 1 + f(n - 1); }
+println(limit_calls(f, 5)(100));
+"#;
+    assert_failure_and_check_stderr(source, "`f` was called more than 5 times");
+}
+
+#[test]
+fn callables_display() {
+    let source = r#"
+fn greet() {}
+class Point {}
+println(greet);
+println(Point);
+println(Point());
+println([greet, Point]);
+"#;
+    let output = "
+<fn greet>
+<class Point>
+<Point instance>
+[<fn greet>, <class Point>]
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn default_parameter_values() {
+    let source = r#"
+fn greet(name, greeting = "Hello", end = greeting + "!") {
+    println(greeting + ", " + name + " " + end);
+}
+greet("Bob");
+greet("Bob", "Hi");
+greet("Bob", "Hi", "?");
+"#;
+    let output = "
+Hello, Bob Hello!
+Hi, Bob Hi!
+Hi, Bob ?
+";
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr(
+        r#"fn greet(name, greeting = "Hello") {} greet();"#,
+        "expected 1 to 2 arguments but got 0",
+    );
+    assert_failure_and_check_stderr(
+        "fn f(a = 1, b) {}",
+        "parameters without a default value must come first",
+    );
+}
+
+#[test]
+fn tail_recursion_does_not_grow_the_stack() {
+    let source = r#"
+fn countdown(n) {
+    if (n == 0) return "liftoff";
+    return countdown(n - 1);
+}
+println(countdown(20000));
+fn sum(n, acc) {
+    if (n == 0) return acc;
+    {
+        let next = n - 1;
+        return sum(next, acc + n);
+    }
+}
+println(sum(100, 0));
+"#;
+    assert_success_and_check_stdout(source, "liftoff\n5050");
+}
+
+#[test]
+fn return_value_evaluated_in_its_scope() {
+    let source = r#"
+fn f() {
+    {
+        let y = 1;
+        return y + 1;
+    }
+}
+println(f());
+"#;
+    assert_success_and_check_stdout(source, "2");
+}
+
+#[test]
+fn decorator_wraps_function() {
+    let source = r#"
+fn log(f) {
+    fn wrapper(x) {
+        println("calling");
+        println(x);
+        return f(x);
+    }
+    return wrapper;
+}
+fn twice(f) {
+    fn wrapper(x) {
+        return f(f(x));
+    }
+    return wrapper;
+}
+@log
+fn square(x) {
+    return x * x;
+}
+println(square(3));
+println(square(4));
+@twice @log
+fn inc(x) {
+    return x + 1;
+}
+println(inc(0));
+"#;
+    let output = "
+calling
+3
+9
+calling
+4
+16
+calling
+0
+calling
+1
+2
+";
+    assert_success_and_check_stdout(source, output);
+    assert_failure_and_check_stderr("@log let x = 1;", "expected `fn` after decorator");
+}
+
+#[test]
+fn recursion_shares_the_interpreter() {
+    let source = r#"
+fn factorial(n) {
+    if (n <= 1) return 1;
+    return n * factorial(n - 1);
+}
+fn count(n) {
+    if (n == 0) return 0;
+    return 1 + count(n - 1);
+}
+println(factorial(10));
+println(count(100));
+"#;
+    assert_success_and_check_stdout(source, "3628800\n100");
+    // the traced statements of the innermost call come from the same interpreter
+    run_with_args(&["--trace"], "fn f(n) { if (n > 0) f(n - 1); } f(2);")
+        .success()
+        .stderr(predicates::str::contains(
+            "[trace] call f(0)\n[trace] If (> n 0)",
+        ));
+}
+
+#[test]
+fn hot_recursive_function() {
+    let source = r#"
+fn fib(n) {
+    if (n < 2) return n;
+    return fib(n - 1) + fib(n - 2);
+}
+println(fib(15));
+let total = 0;
+for (let i = 0; i < 2000; i++) total = total + fib(1);
+println(total);
+"#;
+    assert_success_and_check_stdout(source, "610\n2000");
+}
+
+#[test]
+fn static_method_is_called_on_the_class() {
+    let source = r#"
+class MathX {
+    static fn square(x) {
+        return x * x;
+    }
+}
+println(MathX.square(4));
+"#;
+    assert_success_and_check_stdout(source, "16");
+    assert_failure_and_check_stderr(
+        "class A {} A.missing();",
+        "undefined static method `missing` on class `A`",
+    );
+}
+
+#[test]
+fn getter_is_read_as_a_property() {
+    let source = r#"
+class Circle {
+    fn area {
+        return 3 * this.r * this.r;
+    }
+}
+let c = Circle();
+c.r = 2;
+println(c.area);
+c.r = 3;
+println(c.area);
+"#;
+    assert_success_and_check_stdout(source, "12\n27");
+}
+
+#[test]
+fn to_string_method_controls_printed_output() {
+    let source = r#"
+class Point {
+    fn to_string() {
+        return "(" + this.x + ", " + this.y + ")";
+    }
+}
+let p = Point();
+p.x = 1;
+p.y = 2;
+println(p);
+println("p = " + p);
+println("interpolated ${p}");
+class Plain {}
+println(Plain());
+"#;
+    assert_success_and_check_stdout(
+        source,
+        "(1, 2)\np = (1, 2)\ninterpolated (1, 2)\n<Plain instance>",
+    );
 }
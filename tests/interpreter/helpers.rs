@@ -18,10 +18,6 @@ pub fn assert_success_and_check_stdout(source: &str, output: &str) {
     assert_success(source).stdout(predicates::str::contains(output.trim()));
 }
 
-pub fn assert_success_and_check_stderr(source: &str, output: &str) {
-    assert_success(source).stderr(predicates::str::contains(output.trim()));
-}
-
 pub fn assert_failure(source: &str) -> Assert {
     let mut cmd = Command::cargo_bin("rlox").unwrap();
     cmd.arg("-c")
@@ -35,6 +31,15 @@ pub fn assert_failure_and_check_stderr(source: &str, output: &str) {
     assert_failure(source).stderr(predicates::str::contains(output.trim()));
 }
 
+pub fn run_with_args(args: &[&str], source: &str) -> Assert {
+    let mut cmd = Command::cargo_bin("rlox").unwrap();
+    cmd.args(args)
+        .arg("-c")
+        .arg(source)
+        .timeout(Duration::from_secs(1))
+        .assert()
+}
+
 pub fn check_scanner_error(source: &str, expected_type: ScanErrorType) {
     let mut scanner = Scanner::new(source);
     let err = scanner.scan_tokens().err().unwrap();
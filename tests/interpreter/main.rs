@@ -1,6 +1,17 @@
+// the existing tests predate these lints
+#![allow(clippy::approx_constant, clippy::needless_borrow, clippy::useless_vec)]
+
+mod builtins;
 mod control_flow;
+mod errors;
 mod expression;
 mod functions;
 mod helpers;
+mod optimizer;
+mod options;
+mod repl;
 mod scope;
+mod scripts;
+#[cfg(feature = "serde")]
+mod serialization;
 mod tokens;
@@ -0,0 +1,56 @@
+use crate::helpers::assert_success_and_check_stdout;
+use predicates::boolean::PredicateBooleanExt;
+use rlox::optimizer::eliminate_dead_branches;
+use rlox::parser::{Parser, Stmt};
+use rlox::scanner::Scanner;
+
+fn parse(source: &str) -> Vec<Stmt> {
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens().unwrap();
+    Parser::new(scanner.tokens).parse().unwrap()
+}
+
+#[test]
+fn dead_branch_is_removed() {
+    let statements =
+        eliminate_dead_branches(parse(r#"if (false) println("no"); else println("yes");"#));
+    assert_eq!(statements, parse(r#"println("yes");"#));
+
+    let statements = eliminate_dead_branches(parse(r#"fn f() { if ((true)) { return 1; } }"#));
+    assert_eq!(statements, parse(r#"fn f() { { return 1; } }"#));
+
+    let statements = eliminate_dead_branches(parse(r#"if (false) println("no");"#));
+    assert_eq!(statements, vec![Stmt::Null]);
+}
+
+#[test]
+fn only_literal_conditions_are_eliminated() {
+    let source = r#"let x = false; if (x) println("no"); else println("yes");"#;
+    assert_eq!(eliminate_dead_branches(parse(source)), parse(source));
+}
+
+#[test]
+fn non_boolean_literal_conditions_behave_as_variables() {
+    let source = r#"if (1) println("A"); else println("B");"#;
+    assert_eq!(eliminate_dead_branches(parse(source)), parse(source));
+    assert_success_and_check_stdout(source, "B");
+    assert_success_and_check_stdout(r#"let c = 1; if (c) println("A"); else println("B");"#, "B");
+}
+
+#[test]
+fn dead_branch_is_not_run() {
+    crate::helpers::assert_success(r#"if (false) println("no"); else println("yes");"#)
+        .stdout(predicates::str::contains("yes").and(predicates::str::contains("no").not()));
+    assert_success_and_check_stdout(r#"if (nil) println("no"); else println("yes");"#, "yes");
+}
+
+#[test]
+fn dead_else_if_branches_are_removed() {
+    let statements = eliminate_dead_branches(parse(
+        "if (x) a(); else if (false) b(); else if (true) c(); else if (y) d(); else e();",
+    ));
+    assert_eq!(statements, parse("if (x) a(); else c();"));
+
+    let statements = eliminate_dead_branches(parse("if (false) a(); else if (x) b(); else c();"));
+    assert_eq!(statements, parse("if (x) b(); else c();"));
+}
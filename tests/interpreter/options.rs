@@ -0,0 +1,201 @@
+use crate::helpers::{
+    assert_failure_and_check_stderr, assert_success_and_check_stdout, run_with_args,
+};
+use assert_cmd::Command;
+use std::time::Duration;
+
+#[test]
+fn string_repetition() {
+    let source = r#"
+println("ab" * 3);
+println(2 * "-");
+"#;
+    let output = "
+ababab
+--
+";
+    assert_success_and_check_stdout(source, output);
+}
+
+#[test]
+fn oversized_string_errors_under_max_string_len() {
+    run_with_args(&["--max-string-len", "8"], r#"println("ab" * 4);"#)
+        .success()
+        .stdout(predicates::str::contains("abababab"));
+    run_with_args(&["--max-string-len", "8"], r#"println("ab" * 1000000);"#)
+        .failure()
+        .stderr(predicates::str::contains(
+            "string of length 2000000 exceeds the maximum length of 8",
+        ));
+    run_with_args(&["--max-string-len", "8"], r#"println("abcde" + "fghij");"#)
+        .failure()
+        .stderr(predicates::str::contains("exceeds the maximum length"));
+    run_with_args(&["--max-string-len", "4"], r#"join(["ab", "cd"], ",");"#)
+        .failure()
+        .stderr(predicates::str::contains("exceeds the maximum length"));
+}
+
+#[test]
+fn oversized_string_errors_without_max_string_len() {
+    assert_failure_and_check_stderr(
+        r#"println("ab" * 1000000000000);"#,
+        "string of length 2000000000000 exceeds the maximum length of 1073741824",
+    );
+    assert_failure_and_check_stderr(
+        r#"println("ab" * 100000000000000000000000);"#,
+        "the result is too long",
+    );
+}
+
+#[test]
+fn builtins_are_protected_under_flag() {
+    run_with_args(&["--protect-builtins"], "clock = 5;")
+        .failure()
+        .stderr(predicates::str::contains(
+            "cannot overwrite built-in `clock`",
+        ));
+    run_with_args(&["--protect-builtins"], "let clock = 5;")
+        .failure()
+        .stderr(predicates::str::contains(
+            "cannot overwrite built-in `clock`",
+        ));
+    run_with_args(
+        &["--protect-builtins"],
+        "{ let clock = 5; println(clock); } fn f(len) { return len; } println(f(3));",
+    )
+    .success()
+    .stdout(predicates::str::contains("5\n3"));
+    run_with_args(&[], "let clock = 5; println(clock);")
+        .success()
+        .stdout(predicates::str::contains("5"));
+}
+
+#[test]
+fn errors_are_not_colored_under_no_color() {
+    let mut cmd = Command::cargo_bin("rlox").unwrap();
+    let output = cmd
+        .env("NO_COLOR", "1")
+        .env("CLICOLOR_FORCE", "1")
+        .arg("-c")
+        .arg("println(-nil);")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("runtime error:"));
+    assert!(!stderr.contains('\x1b'));
+}
+
+#[test]
+fn ast_dumps_tree_without_running() {
+    let source = r#"
+fn add(a, b) { return a + b; }
+let x = add(1, 2);
+while (x < 10) x = x * 2;
+println(x);
+"#;
+    let output = "\
+Function add(a, b)
+  Return (+ a b)
+Var x = (call add 1 2)
+While (< x 10)
+  Expr (= x (* x 2))
+Expr (call println x)
+";
+    run_with_args(&["--ast"], source).success().stdout(output);
+    run_with_args(&["--ast"], "let x = ;")
+        .code(65)
+        .stderr(predicates::str::contains("syntax error"));
+}
+
+#[test]
+fn tokens_dumps_token_stream() {
+    run_with_args(&["--tokens"], "let x = 1;\nprint(x);")
+        .success()
+        .stdout(predicates::str::contains("1:1:       Let let\n"))
+        .stdout(predicates::str::contains("1:9:       Number(1.0) 1\n"))
+        .stdout(predicates::str::contains(
+            "2:1:       Identifier(\"print\") print\n",
+        ))
+        .stdout(predicates::str::contains("Eof"));
+    run_with_args(&["--tokens"], "let # = 1;")
+        .code(65)
+        .stderr(predicates::str::contains("unexpected symbol"));
+}
+
+#[test]
+fn dot_dumps_graphviz_digraph() {
+    let output = r#"digraph ast {
+    n0 [label="Expr"];
+    n1 [label="+"];
+    n2 [label="1"];
+    n3 [label="2"];
+    n1 -> n2;
+    n1 -> n3;
+    n0 -> n1;
+}
+"#;
+    run_with_args(&["--dot"], "1 + 2;").success().stdout(output);
+    run_with_args(&["--dot"], r#"println("a\"b");"#)
+        .success()
+        .stdout(predicates::str::contains(r#"[label="\"a\\\"b\""];"#));
+}
+
+#[test]
+fn script_arguments_available_through_args() {
+    let path = std::env::temp_dir().join(format!("rlox-args-{}.lox", std::process::id()));
+    std::fs::write(&path, "println(args());").unwrap();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg(&path)
+        .args(["one", "two words", "--ast"])
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stdout("[\"one\", \"two words\", \"--ast\"]\n");
+    std::fs::remove_file(&path).unwrap();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["-c", "println(len(args()));", "x", "y"])
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stdout("2\n");
+}
+
+#[test]
+fn trace_prints_statements_and_calls() {
+    let source = r#"
+fn greet(name) {
+    println("hi " + name);
+}
+greet("Bob");
+"#;
+    run_with_args(&["--trace"], source)
+        .success()
+        .stdout("hi Bob\n")
+        .stderr(predicates::str::contains("[trace] Function greet(name)\n"))
+        .stderr(predicates::str::contains("[trace] call greet(\"Bob\")\n"))
+        .stderr(predicates::str::contains(
+            "[trace] Expr (call println (+ \"hi \" name))\n",
+        ));
+    run_with_args(&[], source).success().stderr("");
+}
+
+#[test]
+fn expression_flag_prints_the_value() {
+    let rlox = |args: &[&str]| {
+        Command::cargo_bin("rlox")
+            .unwrap()
+            .args(args)
+            .timeout(Duration::from_secs(1))
+            .assert()
+    };
+    rlox(&["-e", "3 * 4"]).success().stdout("12\n");
+    rlox(&["-e", "\"a\" + \"b\""]).success().stdout("ab\n");
+    rlox(&["-e", "1; 2"])
+        .code(65)
+        .stderr(predicates::str::contains("expected end of expression"));
+    rlox(&["-e", "1 / 0"])
+        .code(70)
+        .stderr(predicates::str::contains("division by zero"));
+}
@@ -0,0 +1,119 @@
+use assert_cmd::Command;
+use std::time::Duration;
+
+#[test]
+fn reset_clears_user_bindings() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("-i")
+        .write_stdin("let x = 1;\nprintln(x);\n:reset\nprintln(x);\nprintln(clock != nil);\n")
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("1\n"))
+        .stdout(predicates::str::contains("true"))
+        .stderr(predicates::str::contains("name `x` is not defined"));
+}
+
+#[test]
+fn show_changes_reports_global_bindings() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["-i", "--show-changes"])
+        .write_stdin("let x = 1;\nx = 2;\nprintln(x);\n")
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("added: x\nchanged: x\n"));
+}
+
+#[test]
+fn failed_block_expression_leaves_its_scope() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["-i", "--show-changes"])
+        .write_stdin("let a = { let z = 1; nosuch };\nlet b = 2;\n")
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("added: b\n"));
+}
+
+#[test]
+fn failed_block_statement_leaves_its_scope() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["-i", "--show-changes"])
+        .write_stdin("{ let z = 1; nosuch; }\nlet b = 2;\nprintln(z);\n")
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("added: b\n"))
+        .stderr(predicates::str::contains("name `z` is not defined"));
+}
+
+#[test]
+fn underscore_declared_const_is_kept() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("-i")
+        .write_stdin("const _ = 1;\n2;\nprintln(_);\n")
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("2.0\n> 1\n"))
+        .stderr(predicates::str::contains("cannot redefine const `_`"));
+}
+
+#[test]
+fn infix_functions_are_kept_across_lines() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("-i")
+        .write_stdin(
+            "infix fn avg(a, b) { return (a + b) / 2; }\nprintln(4 avg 6);\n:reset\nprintln(4 avg 6);\n",
+        )
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("> 5\n"))
+        .stderr(predicates::str::contains("expected `)` after arguments"));
+}
+
+#[test]
+fn piped_source_runs_as_script() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .write_stdin("let x = 2;\nprintln(x * 21);\n")
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stdout("42\n");
+}
+
+#[test]
+fn underscore_holds_the_last_value() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("-i")
+        .write_stdin("6 * 7;\nprintln(_ + 1);\nnil;\nprintln(_);\n")
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("43\n"))
+        .stdout(predicates::str::contains("42\n"));
+}
+
+#[test]
+fn echo_escapes_strings() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("-i")
+        .write_stdin("\"a\\nb\";\n[\"q\\\"\", 1];\nprintln(\"c\\nd\");\n")
+        .timeout(Duration::from_secs(1))
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"a\\nb\"\n"))
+        .stdout(predicates::str::contains("[\"q\\\"\", 1.0]\n"))
+        .stdout(predicates::str::contains("c\nd\n"));
+}
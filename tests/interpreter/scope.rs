@@ -1,4 +1,11 @@
-use crate::helpers::assert_success_and_check_stdout;
+use crate::helpers::{
+    assert_failure, assert_failure_and_check_stderr, assert_success_and_check_stdout,
+};
+use predicates::prelude::*;
+use rlox::interpreter::{Environment, Interpreter};
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::symbol::Symbol;
 
 #[test]
 fn nested_blocks() {
@@ -11,17 +18,17 @@ let c = "global c";
   let b = "outer b";
   {
     let a = "inner a";
-    print(a);
-    print(b);
-    print(c);
+    println(a);
+    println(b);
+    println(c);
   }
-  print(a);
-  print(b);
-  print(c);
+  println(a);
+  println(b);
+  println(c);
 }
-print(a);
-print(b);
-print(c);
+println(a);
+println(b);
+println(c);
 "#;
     let output = "
 inner a
@@ -36,3 +43,238 @@ global c
 ";
     assert_success_and_check_stdout(source, output);
 }
+
+#[test]
+fn const_bindings_cannot_be_reassigned() {
+    let source = r#"
+const limit = 3;
+println(limit * 2);
+{
+    let limit = 1;
+    limit = 2;
+    println(limit);
+}
+limit = 4;
+println("unreachable");
+"#;
+    assert_failure(source)
+        .code(70)
+        .stdout(predicates::str::contains("6\n2\n"))
+        .stdout(predicates::str::contains("unreachable").not())
+        .stderr(predicates::str::contains("cannot assign to const `limit`"));
+    assert_failure_and_check_stderr("const x;", "constants must be initialized");
+}
+
+#[test]
+fn const_bindings_cannot_be_redefined() {
+    for source in [
+        "const x = 1; let x = 2; x = 3; println(x);",
+        "const x = 1; const x = 2; println(x);",
+        "const f = 1; fn f() {} println(f);",
+    ] {
+        assert_failure(source)
+            .code(70)
+            .stdout("")
+            .stderr(predicates::str::contains("cannot redefine const"));
+    }
+}
+
+#[test]
+fn uninitialized_variable_error_names_the_line() {
+    let source = r#"
+let x;
+println("before");
+println(x);
+"#;
+    assert_failure(source)
+        .code(70)
+        .stdout(predicates::str::contains("before"))
+        .stderr(predicates::str::contains(
+            "line 4: variable `x` used uninitialized",
+        ));
+}
+
+#[test]
+fn reused_scopes_do_not_leak_bindings() {
+    let source = r#"
+for (let i = 0; i < 3; i = i + 1) {
+    if (i == 0) {
+        let secret = "first";
+        println(secret);
+    } else {
+        let seen = "unset";
+        {
+            let secret = "inner ${i}";
+            seen = secret;
+        }
+        println(seen);
+    }
+}
+{
+    let a = 1;
+}
+{
+    println(a);
+}
+"#;
+    assert_failure(source)
+        .code(70)
+        .stdout(predicates::str::contains("first\ninner 1\ninner 2\n"))
+        .stderr(predicates::str::contains("name `a` is not defined"));
+}
+
+#[test]
+fn global_initializer_reads_previous_binding() {
+    let source = r#"
+let x = 1;
+let x = x + 1;
+println(x);
+"#;
+    assert_success_and_check_stdout(source, "2");
+}
+
+#[test]
+fn local_initializer_cannot_read_declared_variable() {
+    let sources = [
+        "let a = 1; { let a = a; }",
+        "let x = 1; { let x = x + 10; }",
+        "fn f() { let y = [y]; }",
+        "for (let i = i; i < 3; i++) {}",
+        "{ const c = -c; }",
+    ];
+    for source in sources {
+        assert_failure_and_check_stderr(
+            source,
+            "cannot read local variable in its own initializer",
+        );
+    }
+    assert_success_and_check_stdout("let a = 1; { let b = a; println(b); }", "1");
+}
+
+#[test]
+fn nested_function_is_local_to_its_body() {
+    let source = r#"
+fn outer() {
+    fn inner() {
+        return "inner";
+    }
+    println(inner());
+}
+outer();
+println(inner());
+"#;
+    assert_failure(source)
+        .stdout("inner\n")
+        .stderr(predicates::str::contains("name `inner` is not defined"));
+}
+
+#[test]
+fn repeated_identifiers_resolve_to_their_own_bindings() {
+    let source = r#"
+let total = 0;
+fn add(total, value) {
+    return total + value;
+}
+for (let value = 0; value < 50; value++) {
+    let total_before = total;
+    {
+        let total = add(total_before, value);
+        total = total + 0;
+    }
+    total = add(total, value);
+}
+let value = "value";
+println(total);
+println(value);
+"#;
+    assert_success_and_check_stdout(source, "1225\nvalue");
+}
+
+#[test]
+fn shadowing_and_closures_keep_their_own_slots() {
+    let source = r#"
+let a = "global";
+fn make() {
+    let a = "captured";
+    const b = "const";
+    fn get() {
+        return a + " " + b;
+    }
+    return get;
+}
+let get = make();
+{
+    let a = "block";
+    a = a + " again";
+    println(a);
+    println(get());
+}
+let b = 1;
+let b = 2;
+b = 3;
+println(a);
+println(b);
+"#;
+    assert_success_and_check_stdout(source, "block again\ncaptured const\nglobal\n3");
+}
+
+#[test]
+fn redeclaring_local_in_same_scope_is_an_error() {
+    let sources = [
+        "{ let x = 1; let x = 2; }",
+        "fn f() { let x; const x = 1; }",
+        "for (let i = 0; i < 1; i++) { let y; let y; }",
+    ];
+    for source in sources {
+        assert_failure_and_check_stderr(source, "already declared in this scope");
+    }
+}
+
+#[test]
+fn shadowing_in_nested_scope_is_allowed() {
+    let source = r#"
+let x = 1;
+let x = 2;
+{
+    let x = 3;
+    {
+        let x = 4;
+        println(x);
+    }
+    println(x);
+}
+println(x);
+"#;
+    assert_success_and_check_stdout(source, "4\n3\n2");
+}
+
+#[test]
+fn restoring_a_snapshot_reverts_global_bindings() {
+    let eval = |env: &mut Environment, source: &str| {
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().unwrap();
+        let statements = Parser::new(scanner.tokens).parse().unwrap();
+        Interpreter::new().interpret(env, &statements).unwrap();
+    };
+    let mut env = Environment::new();
+    eval(&mut env, "let kept = 1;");
+    let snapshot = env.snapshot();
+    eval(&mut env, "let added = 2; kept = 3;");
+    assert!(env.get(Symbol::intern("added")).is_ok());
+    env.restore(snapshot);
+    assert!(env.get(Symbol::intern("added")).is_err());
+    let kept = env.get(Symbol::intern("kept")).unwrap();
+    assert_eq!(
+        kept.as_ref().map(|value| value.to_string()),
+        Some("1".to_string())
+    );
+}
+
+#[test]
+fn large_scopes_are_indexed_by_name() {
+    let declarations: String = (0..40).map(|i| format!("let v{i} = {i};")).collect();
+    let source = format!(
+        "{declarations} {{ {declarations} v39 = 0; println(v39 + v20); }} println(v39); v5 = 50; println(v5);"
+    );
+    assert_success_and_check_stdout(&source, "20\n39\n50");
+}
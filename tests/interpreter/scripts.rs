@@ -0,0 +1,38 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+#[test]
+fn lox_scripts_exit_successfully() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lox");
+    let mut scripts = fs::read_dir(dir)
+        .expect("could not read the lox scripts directory")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect::<Vec<_>>();
+    scripts.sort();
+    assert!(!scripts.is_empty(), "no lox scripts were found");
+
+    let failures = scripts
+        .iter()
+        .filter(|script| {
+            let output = Command::cargo_bin("rlox")
+                .unwrap()
+                .arg(script)
+                .timeout(Duration::from_secs(5))
+                .output()
+                .unwrap();
+            if !output.status.success() {
+                eprintln!(
+                    "{} failed ({}):\n{}",
+                    script.display(),
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+            !output.status.success()
+        })
+        .count();
+    assert_eq!(failures, 0, "{failures} lox script(s) failed");
+}
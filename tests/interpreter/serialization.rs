@@ -0,0 +1,37 @@
+use rlox::expression::Object;
+use rlox::functions::Print;
+use rlox::parser::{Parser, Stmt};
+use rlox::scanner::Scanner;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn parsed_program_round_trips_through_json() {
+    let source = r#"
+fn greet(name, greeting = "Hello") {
+    return greeting + ", " + name + "!";
+}
+let people = ["Ada", "Alan"];
+for (person in people) println(greet(person));
+"#;
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens().unwrap();
+    let statements = Parser::new(scanner.tokens).parse().unwrap();
+    let json = serde_json::to_string(&statements).unwrap();
+    let decoded: Vec<Stmt> = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, statements);
+}
+
+#[test]
+fn callables_serialize_as_name_and_arity() {
+    let object = Object::Array(Rc::new(RefCell::new(vec![
+        Object::Number(1.0),
+        Object::Callable(Rc::new(RefCell::new(Print))),
+    ])));
+    let json = serde_json::to_string(&object).unwrap();
+    assert_eq!(
+        json,
+        r#"{"Array":[{"Number":1.0},{"Callable":{"name":"print","arity":1}}]}"#
+    );
+    assert!(serde_json::from_str::<Object>(&json).is_err());
+}
@@ -2,6 +2,7 @@ use crate::helpers::{assert_failure_and_check_stderr, check_scanner_error};
 use claim::assert_ok;
 use rlox::errors::ScanErrorType::*;
 use rlox::scanner::*;
+use rlox::symbol::Symbol;
 
 #[test]
 fn simple_expression_tokenized_correctly() {
@@ -12,7 +13,7 @@ fn simple_expression_tokenized_correctly() {
 
     let types = vec![
         TokenType::Let,
-        TokenType::Identifier("name".to_string()),
+        TokenType::Identifier(Symbol::intern("name")),
         TokenType::Equal,
         TokenType::Str("Alphonse".to_string()),
         TokenType::Semicolon,
@@ -34,7 +35,7 @@ fn unterminated_string_returns_error() {
     let source = r#"
 let x = "Louis";
 let a = 4;
-print("Alphonse + Louis);
+println("Alphonse + Louis);
 "#;
     assert_failure_and_check_stderr(source, "unterminated string");
 }
@@ -45,12 +46,20 @@ fn invalid_number_returns_error() {
     check_scanner_error(source, InvalidNumber);
 }
 
+#[test]
+fn number_too_large_returns_error() {
+    let source = format!("let x = {};", "9".repeat(400));
+    check_scanner_error(&source, InvalidNumber);
+    assert_failure_and_check_stderr(&source, "number too large");
+    check_scanner_error(&format!("1{}.5", "0".repeat(400)), InvalidNumber);
+}
+
 #[test]
 fn invalid_expressions_return_error() {
     let cases = [
         ("let x = 1253.f", InvalidNumber),
         (r#"let x = 12. name = "Alphonse""#, InvalidNumber),
-        ("let @pi = 3.415", UnexpectedCharacter),
+        ("let #pi = 3.415", UnexpectedCharacter),
         (
             r#"let name = "Alphonse; var x = 3.1415"#,
             UnterminatedString,
@@ -60,3 +69,104 @@ fn invalid_expressions_return_error() {
         check_scanner_error(source, error_type);
     }
 }
+
+#[test]
+fn string_escapes_decoded() {
+    let cases = [
+        (r#""\x41""#, "A"),
+        (r#""\u{1F600}""#, "😀"),
+        (r#""a\tb\n\"c\"\\""#, "a\tb\n\"c\"\\"),
+    ];
+    for (source, expected) in cases {
+        let mut scanner = Scanner::new(source);
+        assert_ok!(scanner.scan_tokens());
+        assert_eq!(
+            scanner.tokens[0].r#type,
+            TokenType::Str(expected.to_string())
+        );
+    }
+}
+
+#[test]
+fn invalid_string_escapes_return_error() {
+    for source in [
+        r#""\x4g""#,
+        r#""\u{110000}""#,
+        r#""\u{}""#,
+        r#""\u41""#,
+        r#""\q""#,
+    ] {
+        check_scanner_error(source, InvalidEscape);
+    }
+}
+
+#[test]
+fn triple_quoted_string_spans_lines() {
+    let source = "let s = \"\"\"first \"line\"\nsecond\"\"\";\nlet t = 1;";
+    let mut scanner = Scanner::new(source);
+    assert_ok!(scanner.scan_tokens());
+    assert_eq!(
+        scanner.tokens[3].r#type,
+        TokenType::Str("first \"line\"\nsecond".to_string())
+    );
+    assert_eq!(scanner.tokens[3].line, 1);
+    assert_eq!(scanner.tokens[5].line, 3);
+    check_scanner_error("\"\"\"never closed\"\"", UnterminatedString);
+}
+
+#[test]
+fn unterminated_interpolation_returns_error() {
+    check_scanner_error(r#"let s = "value: ${x";"#, UnterminatedString);
+}
+
+#[test]
+fn comments_preserved_on_demand() {
+    let source = "1; // hi";
+    let mut scanner = Scanner::with_comments(source);
+    assert_ok!(scanner.scan_tokens());
+    let comment = &scanner.tokens[2];
+    assert_eq!(comment.r#type, TokenType::Comment(" hi".to_string()));
+    assert_eq!((comment.line, comment.col), (1, 4));
+
+    let mut scanner = Scanner::new(source);
+    assert_ok!(scanner.scan_tokens());
+    assert!(scanner
+        .tokens
+        .iter()
+        .all(|token| !matches!(token.r#type, TokenType::Comment(_))));
+
+    let mut scanner = Scanner::with_comments(source);
+    assert_ok!(scanner.scan_tokens());
+    assert_ok!(rlox::parser::Parser::new(scanner.tokens).parse());
+}
+
+#[test]
+fn crlf_line_endings() {
+    let source = "let a = 1; // one\r\nlet s = 2;\r\n  let b = 3;\r\n";
+    let mut scanner = Scanner::with_comments(source);
+    assert_ok!(scanner.scan_tokens());
+    let tokens = scanner.tokens;
+    assert_eq!(tokens[5].r#type, TokenType::Comment(" one".to_string()));
+    let b = &tokens[12];
+    assert_eq!(b.r#type, TokenType::Identifier(Symbol::intern("b")));
+    assert_eq!((b.line, b.col), (3, 7));
+
+    let mut scanner = Scanner::new("\"\"\"x\r\ny\"\"\"");
+    assert_ok!(scanner.scan_tokens());
+    assert_eq!(scanner.tokens[0].r#type, TokenType::Str("x\ny".to_string()));
+}
+
+#[test]
+fn columns_count_characters_after_multi_byte_characters_and_tabs() {
+    let mut scanner = Scanner::new("let s = \"héllo\"; 12;\n\tx;");
+    assert_ok!(scanner.scan_tokens());
+    let positions: Vec<_> = scanner
+        .tokens
+        .iter()
+        .map(|t| (t.lexeme.as_str(), t.line, t.col))
+        .collect();
+    assert_eq!(positions[3], ("\"héllo\"", 1, 9));
+    assert_eq!(positions[4], (";", 1, 16));
+    assert_eq!(positions[5], ("12", 1, 18));
+    assert_eq!(positions[7], ("x", 2, 2));
+}